@@ -0,0 +1,355 @@
+use cursive::theme::{BaseColor, Color, ColorType, Effect, Style};
+
+/// 解析后的终端指令，由 LogBuffer 按顺序执行
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnsiAction {
+    /// 以指定样式在光标处输出文本（不包含控制字符）
+    Text(String, Style),
+    /// `\n`
+    NewLine,
+    /// `\r`
+    CarriageReturn,
+    /// `\t`，补齐的空格使用当时的样式
+    Tab(Style),
+    /// `ESC[nA`
+    CursorUp(usize),
+    /// `ESC[nB`
+    CursorDown(usize),
+    /// `ESC[nC`
+    CursorForward(usize),
+    /// `ESC[nD` 或者退格 `\x08`
+    CursorBack(usize),
+    /// `ESC[nG`，从 0 开始的列号
+    CursorColumn(usize),
+    /// `ESC[row;colH`，从 0 开始的行列号，相对于最近一次清屏的位置
+    CursorPosition(usize, usize),
+    /// `ESC[nK`
+    EraseLine(EraseMode),
+    /// `ESC[nJ`；`ESC[2J`、`ESC[3J` 与 `ESC c` 都视为清屏
+    EraseDisplay(EraseMode),
+}
+
+/// 擦除的范围，对应 `K`/`J` 指令的参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EraseMode {
+    /// 从光标到末尾
+    ToEnd,
+    /// 从开头到光标
+    ToStart,
+    /// 全部
+    All,
+}
+
+/// 等待后续输入的未完成转义序列的最大长度
+const MAX_ESCAPE_LEN: usize = 4096;
+/// CSI 参数的上限，`ESC[999999999C` 之类的参数按它处理
+pub const MAX_CSI_ARG: usize = 9999;
+
+/// 增量的 ANSI 解析器：SGR 样式会跨越多次写入保持，
+/// 被截断在两次写入之间的转义序列会缓存到下一次写入时再解析
+pub struct AnsiParser {
+    style: Style,
+    pending: String,
+}
+
+impl AnsiParser {
+    pub fn new() -> Self {
+        AnsiParser {
+            style: Style::none(),
+            pending: String::new(),
+        }
+    }
+
+    pub fn parse(&mut self, input: &str) -> Vec<AnsiAction> {
+        let mut text = std::mem::take(&mut self.pending);
+        text.push_str(input);
+
+        let mut actions = Vec::new();
+        let mut plain = String::new();
+        let mut chars = text.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            // 转义序列可能改变样式，之前的文本要以原来的样式输出
+            if c == '\x1b' && !plain.is_empty() {
+                actions.push(AnsiAction::Text(std::mem::take(&mut plain), self.style));
+            }
+            let control = match c {
+                '\n' => AnsiAction::NewLine,
+                '\r' => AnsiAction::CarriageReturn,
+                '\t' => AnsiAction::Tab(self.style),
+                '\x08' => AnsiAction::CursorBack(1),
+                '\x1b' => match self.parse_escape(&text[start..]) {
+                    // 过长的未完成序列不再等待，直接丢弃，避免吞掉之后所有的输出
+                    Escape::Incomplete if text.len() - start > MAX_ESCAPE_LEN => break,
+                    Escape::Incomplete => {
+                        self.pending = text[start..].to_owned();
+                        break;
+                    }
+                    Escape::Parsed(len, action) => {
+                        // 跳过整个转义序列
+                        while chars.peek().is_some_and(|&(i, _)| i < start + len) {
+                            chars.next();
+                        }
+                        match action {
+                            Some(action) => action,
+                            None => continue,
+                        }
+                    }
+                },
+                c if c.is_control() => continue,
+                c => {
+                    plain.push(c);
+                    continue;
+                }
+            };
+            if !plain.is_empty() {
+                actions.push(AnsiAction::Text(std::mem::take(&mut plain), self.style));
+            }
+            actions.push(control);
+        }
+        if !plain.is_empty() {
+            actions.push(AnsiAction::Text(plain, self.style));
+        }
+        actions
+    }
+
+    /// 解析以 ESC 开头的序列，返回序列的字节长度和对应的指令；样式指令直接更新解析器的状态
+    fn parse_escape(&mut self, text: &str) -> Escape {
+        let bytes = text.as_bytes();
+        match bytes.get(1) {
+            None => Escape::Incomplete,
+            // CSI
+            Some(b'[') => {
+                let end = match bytes[2..].iter().position(|b| (0x40..=0x7e).contains(b)) {
+                    Some(end) => end + 2,
+                    None => return Escape::Incomplete,
+                };
+                let action = self.apply_csi(&text[2..end], bytes[end]);
+                Escape::Parsed(end + 1, action)
+            }
+            // OSC：以 BEL 或者 ST 结尾，例如设置窗口标题、超链接；
+            // 遇到换行时视为序列已经中断，换行照常输出
+            Some(b']') => {
+                for (i, b) in bytes.iter().enumerate().skip(2) {
+                    if *b == b'\n' {
+                        return Escape::Parsed(i, None);
+                    }
+                    if *b == 0x07 {
+                        return Escape::Parsed(i + 1, None);
+                    }
+                    if *b == 0x1b && bytes.get(i + 1) == Some(&b'\\') {
+                        return Escape::Parsed(i + 2, None);
+                    }
+                }
+                Escape::Incomplete
+            }
+            // RIS：tsc --watch 用它来清屏
+            Some(b'c') => {
+                self.style = Style::none();
+                Escape::Parsed(2, Some(AnsiAction::EraseDisplay(EraseMode::All)))
+            }
+            // 字符集选择等三字节的序列
+            Some(b'(') | Some(b')') => {
+                if bytes.len() < 3 {
+                    Escape::Incomplete
+                } else {
+                    Escape::Parsed(3, None)
+                }
+            }
+            Some(_) => Escape::Parsed(2, None),
+        }
+    }
+
+    fn apply_csi(&mut self, params: &str, command: u8) -> Option<AnsiAction> {
+        // 私有模式（例如 `ESC[?25l` 隐藏光标）与日志无关
+        if params.starts_with('?') {
+            return None;
+        }
+        let args: Vec<usize> = params
+            .split(';')
+            .map(|arg| arg.parse().map_or(0, |n: usize| n.min(MAX_CSI_ARG)))
+            .collect();
+        let arg = |index: usize, default: usize| match args.get(index) {
+            Some(0) | None => default,
+            Some(&n) => n,
+        };
+        let erase_mode = || match args.first() {
+            Some(1) => EraseMode::ToStart,
+            Some(2) | Some(3) => EraseMode::All,
+            _ => EraseMode::ToEnd,
+        };
+        match command {
+            b'm' => {
+                self.apply_sgr(&args);
+                None
+            }
+            b'A' => Some(AnsiAction::CursorUp(arg(0, 1))),
+            b'B' => Some(AnsiAction::CursorDown(arg(0, 1))),
+            b'C' => Some(AnsiAction::CursorForward(arg(0, 1))),
+            b'D' => Some(AnsiAction::CursorBack(arg(0, 1))),
+            b'G' => Some(AnsiAction::CursorColumn(arg(0, 1) - 1)),
+            b'H' | b'f' => Some(AnsiAction::CursorPosition(arg(0, 1) - 1, arg(1, 1) - 1)),
+            b'K' => Some(AnsiAction::EraseLine(erase_mode())),
+            b'J' => Some(AnsiAction::EraseDisplay(erase_mode())),
+            _ => None,
+        }
+    }
+
+    fn apply_sgr(&mut self, args: &[usize]) {
+        let mut args = args.iter().copied();
+        while let Some(code) = args.next() {
+            match code {
+                0 => self.style = Style::none(),
+                1 => self.set_effect(Effect::Bold, true),
+                2 => self.set_effect(Effect::Dim, true),
+                3 => self.set_effect(Effect::Italic, true),
+                4 => self.set_effect(Effect::Underline, true),
+                5 | 6 => self.set_effect(Effect::Blink, true),
+                7 => self.set_effect(Effect::Reverse, true),
+                9 => self.set_effect(Effect::Strikethrough, true),
+                22 => {
+                    self.set_effect(Effect::Bold, false);
+                    self.set_effect(Effect::Dim, false);
+                }
+                23 => self.set_effect(Effect::Italic, false),
+                24 => self.set_effect(Effect::Underline, false),
+                25 => self.set_effect(Effect::Blink, false),
+                27 => self.set_effect(Effect::Reverse, false),
+                29 => self.set_effect(Effect::Strikethrough, false),
+                30..=37 => self.style.color.front = base_color(code - 30, false),
+                90..=97 => self.style.color.front = base_color(code - 90, true),
+                39 => self.style.color.front = ColorType::InheritParent,
+                40..=47 => self.style.color.back = base_color(code - 40, false),
+                100..=107 => self.style.color.back = base_color(code - 100, true),
+                49 => self.style.color.back = ColorType::InheritParent,
+                38 | 48 => {
+                    let color = match args.next() {
+                        Some(5) => args.next().map(|n| Color::from_256colors(n as u8)),
+                        Some(2) => {
+                            let r = args.next().unwrap_or(0) as u8;
+                            let g = args.next().unwrap_or(0) as u8;
+                            let b = args.next().unwrap_or(0) as u8;
+                            Some(Color::Rgb(r, g, b))
+                        }
+                        _ => None,
+                    };
+                    if let Some(color) = color {
+                        if code == 38 {
+                            self.style.color.front = ColorType::Color(color);
+                        } else {
+                            self.style.color.back = ColorType::Color(color);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn set_effect(&mut self, effect: Effect, enabled: bool) {
+        if enabled {
+            self.style.effects.insert(effect);
+        } else {
+            self.style.effects.remove(effect);
+        }
+    }
+}
+
+enum Escape {
+    /// 序列还没有写完整
+    Incomplete,
+    Parsed(usize, Option<AnsiAction>),
+}
+
+fn base_color(index: usize, light: bool) -> ColorType {
+    let base = [
+        BaseColor::Black,
+        BaseColor::Red,
+        BaseColor::Green,
+        BaseColor::Yellow,
+        BaseColor::Blue,
+        BaseColor::Magenta,
+        BaseColor::Cyan,
+        BaseColor::White,
+    ][index];
+    let color = if light { base.light() } else { base.dark() };
+    ColorType::Color(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text_of(actions: &[AnsiAction]) -> String {
+        actions
+            .iter()
+            .map(|action| match action {
+                AnsiAction::Text(text, _) => text.as_str(),
+                AnsiAction::NewLine => "\n",
+                _ => "",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sgr_applies_to_the_following_text_only() {
+        let mut parser = AnsiParser::new();
+        let red = Style::from(BaseColor::Red.dark());
+        assert_eq!(
+            parser.parse("a\x1b[31mred\x1b[0m b"),
+            [
+                AnsiAction::Text("a".to_owned(), Style::none()),
+                AnsiAction::Text("red".to_owned(), red),
+                AnsiAction::Text(" b".to_owned(), Style::none()),
+            ]
+        );
+        // 样式跨越多次写入保持
+        assert_eq!(
+            parser.parse("\x1b[1;31mx"),
+            [AnsiAction::Text("x".to_owned(), red.combine(Effect::Bold))]
+        );
+        assert_eq!(
+            parser.parse("y\x1b[22m"),
+            [AnsiAction::Text("y".to_owned(), red.combine(Effect::Bold))]
+        );
+    }
+
+    #[test]
+    fn osc_split_across_writes_is_skipped() {
+        let mut parser = AnsiParser::new();
+        assert_eq!(text_of(&parser.parse("a\x1b]0;ti")), "a");
+        assert_eq!(text_of(&parser.parse("tle\x07b\n")), "b\n");
+    }
+
+    #[test]
+    fn unterminated_osc_ends_at_newline() {
+        let mut parser = AnsiParser::new();
+        assert_eq!(text_of(&parser.parse("\x1b]0;title")), "");
+        assert_eq!(text_of(&parser.parse("hello\nworld\n")), "\nworld\n");
+    }
+
+    #[test]
+    fn csi_arguments_are_clamped() {
+        let mut parser = AnsiParser::new();
+        assert_eq!(
+            parser.parse("\x1b[4000000000B\x1b[999999999C\x1b[99999999;1H"),
+            [
+                AnsiAction::CursorDown(MAX_CSI_ARG),
+                AnsiAction::CursorForward(MAX_CSI_ARG),
+                AnsiAction::CursorPosition(MAX_CSI_ARG - 1, 0),
+            ]
+        );
+        // 超出 usize 范围的参数按缺省值处理
+        assert_eq!(
+            parser.parse("\x1b[99999999999999999999999A"),
+            [AnsiAction::CursorUp(1)]
+        );
+    }
+
+    #[test]
+    fn overlong_escape_is_dropped() {
+        let mut parser = AnsiParser::new();
+        parser.parse("\x1b[");
+        parser.parse(&"1;".repeat(MAX_ESCAPE_LEN));
+        assert_eq!(text_of(&parser.parse("next")), "next");
+    }
+}
@@ -1,31 +1,47 @@
+use crate::editor;
+use crate::menu::{self, MenuEntry, MenuSection};
 use crate::page::*;
+use crate::page_tab::TabStatus;
+use crate::runner::Runner;
 
 use crate::browser_content::BrowserContentViewer;
+use crate::status_bar::BrowserStatusBarViewer;
 use crate::tabbar::*;
 use cursive::{
     direction::Direction,
-    event::{AnyCb, Event, EventResult},
+    event::{AnyCb, Event, EventResult, Key, MouseButton, MouseEvent},
     theme::{BaseColor, Color, PaletteColor, Theme},
-    view::{CannotFocus, Resizable, Selector, View, ViewNotFound, ViewWrapper},
-    views::{Layer, LinearLayout, ResizedView, ThemedView},
+    utils::markup::StyledString,
+    view::{CannotFocus, Resizable, Scrollable, Selector, View, ViewNotFound},
+    views::{Dialog, DialogFocus, Layer, LinearLayout, ResizedView, TextView, ThemedView},
     Printer, Rect, Vec2, With,
 };
 
 use std::cell::RefCell;
-use std::cmp::max;
+use std::cmp::min;
 use std::collections::HashMap;
 use std::rc::Rc;
 
+/// 客户端断开后，它保留下来的页面上显示的状态原因
+pub const DISCONNECTED_REASON: &str = "client disconnected";
+
+/// 浏览器视图，需要以 `id` 作为名字注册到 cursive 中（`browser.with_name(id)`），
+/// 弹窗等异步回调会通过这个名字找回 Browser
 // #[derive(Clone)]
 pub struct Browser {
     id: String,
     // siv: &'static CursiveRunnable,
     // siv_caller: Box<dyn FnOnce(&mut CursiveRunnable) -> dyn Any>,
-    pages: HashMap<String, Rc<RefCell<Page>>>,
+    /// 页面按照 tab 栏的顺序排列，下标即 tab 的位置
+    pages: Vec<Rc<RefCell<Page>>>,
     view_bar: Rc<RefCell<BrowserTabBarViewer>>,
     view_content: BrowserContentViewer,
+    view_status: BrowserStatusBarViewer,
     view: ResizedView<LinearLayout>,
     selected_page_index: usize,
+    /// 已连接的控制协议客户端，值是它当前使用的连接编号；
+    /// 客户端用新连接重新接入后，旧连接的断开不会影响它的页面
+    clients: HashMap<String, usize>,
 }
 impl Browser {
     pub fn new(id: String /* siv_caller: F */) -> Self
@@ -37,6 +53,7 @@ impl Browser {
         let _tabbar_id = id.clone() + "::tab";
         let bar = Rc::new(RefCell::new(BrowserTabBarViewer::new()));
         let content = BrowserContentViewer::new();
+        let status = BrowserStatusBarViewer::new();
         // let content_wrapper =
         Browser {
             id,
@@ -53,73 +70,318 @@ impl Browser {
                     }),
                     Layer::new(content.clone()),
                 ))
+                .child(status.clone())
                 .full_screen(),
-            pages: HashMap::new(),
+            pages: Vec::new(),
             view_bar: bar,
             view_content: content,
+            view_status: status,
             selected_page_index: 0,
+            clients: HashMap::new(),
         }
     }
-    fn with_layout<F, R>(&self, f: F) -> R
-    where
-        F: FnOnce(&LinearLayout) -> R,
-    {
-        self.view.with_view(f).unwrap()
-    }
-    fn with_layout_mut<F, R>(&mut self, f: F) -> R
-    where
-        F: FnOnce(&mut LinearLayout) -> R,
-    {
-        self.view.with_view_mut(f).unwrap()
-    }
     fn with_tabbar<F, R>(&self, f: F) -> R
     where
         F: FnOnce(&BrowserTabBarViewer) -> R,
     {
-        f(&*self.view_bar.borrow())
+        f(&self.view_bar.borrow())
     }
-    fn with_tabbar_mut<F, R>(&mut self, mut f: F) -> R
+    fn with_tabbar_mut<F, R>(&mut self, f: F) -> R
     where
         F: FnOnce(&mut BrowserTabBarViewer) -> R,
     {
         f(&mut self.view_bar.borrow_mut())
     }
-    fn with_content<F, R>(&self, f: F) -> R
-    where
-        F: FnOnce(&LinearLayout) -> R,
-    {
-        f(&*self.view_content.view.borrow())
-    }
-    fn with_content_mut<F, R>(&mut self, mut f: F) -> R
+    fn with_content_mut<F, R>(&mut self, f: F) -> R
     where
         F: FnOnce(&mut LinearLayout) -> R,
     {
         f(&mut self.view_content.view.borrow_mut())
     }
 
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn set_tab_width_policy(&mut self, policy: TabWidthPolicy) {
+        self.with_tabbar_mut(|bar| bar.set_width_policy(policy));
+    }
+
     pub fn add_page(&mut self, uri: String) {
-        let page = Page::new(uri.clone());
-        let page_rc = Rc::new(RefCell::new(page.clone()));
-        self.with_tabbar_mut(|bar| {
-            bar.add_tab(page_rc.borrow().tab.clone());
-        });
-        self.pages.insert(uri, page_rc);
+        self.insert_page_at(self.pages.len(), uri);
+    }
+    /// 在指定位置插入页面，并选中它；如果页面已经存在，那么只会选中它
+    pub fn insert_page_at(&mut self, index: usize, uri: String) {
+        if let Some(exists_index) = self.page_index(&uri) {
+            self.select_page_by_index(exists_index);
+            return;
+        }
+        let index = min(index, self.pages.len());
+        let page = Page::new(uri);
+        self.pages.insert(index, Rc::new(RefCell::new(page)));
 
         // 渲染
-        self.selected_page_index = self.pages.len() - 1;
+        self.selected_page_index = index;
         self.render_select_page();
     }
+    /// 直接关闭页面：执行页面的 on_close 钩子，移除 tab，然后选中相邻的页面
     pub fn del_page(&mut self, uri: String) {
-        if let Some(_del_page) = self.pages.remove(&uri) {
+        if let Some(index) = self.page_index(&uri) {
+            let page = self.pages.remove(index);
+            page.borrow_mut().close();
+            // 关闭的是选中页时，下标不变即选中右侧的邻居；如果它是最后一个，渲染时会回退到左侧的邻居
+            if index < self.selected_page_index {
+                self.selected_page_index -= 1;
+            }
             self.render_select_page()
         }
     }
+    /// 请求关闭页面，忙碌中的页面会先弹窗让用户确认
+    pub fn request_close_page(&mut self, uri: String) -> EventResult {
+        let busy = match self.page_index(&uri) {
+            Some(index) => self.pages[index].borrow().is_busy(),
+            None => return EventResult::Ignored,
+        };
+        if !busy {
+            self.del_page(uri);
+            return EventResult::Consumed(None);
+        }
+        let browser_id = self.id.clone();
+        EventResult::with_cb(move |s| {
+            let browser_id = browser_id.clone();
+            let uri = uri.clone();
+            let mut dialog = Dialog::text(format!("{} is still busy, close it anyway?", uri))
+                .button("Close", move |s| {
+                    s.pop_layer();
+                    s.call_on_name(&browser_id, |browser: &mut Browser| {
+                        browser.del_page(uri.clone())
+                    });
+                })
+                .dismiss_button("Cancel")
+                .title("Close page");
+            dialog.set_focus(DialogFocus::Button(1));
+            s.add_layer(dialog);
+        })
+    }
+    /// 弹窗列出选中页面所有生效中的状态及其原因
+    pub fn inspect_selected_page(&mut self) -> EventResult {
+        let page = match self.pages.get(self.selected_page_index) {
+            Some(page) => page.borrow().clone(),
+            None => return EventResult::Ignored,
+        };
+        let tab = page.tab.borrow();
+        let mut content = StyledString::new();
+        for (status, reasons) in tab.status_reasons() {
+            content.append_styled(
+                format!("{} {:?} ({})\n", status.symbol(), status, reasons.len()),
+                status.style(),
+            );
+            for reason in reasons {
+                content.append_plain(format!("  - {}\n", reason));
+            }
+        }
+        if content.is_empty() {
+            content.append_plain("No active status");
+        }
+        let title = format!("Status of {}", tab.title());
+        EventResult::with_cb(move |s| {
+            s.add_layer(
+                Dialog::around(TextView::new(content.clone()).scrollable())
+                    .title(title.clone())
+                    .dismiss_button("Close"),
+            );
+        })
+    }
+    pub fn request_close_selected_page(&mut self) -> EventResult {
+        match self.selected_uri() {
+            Some(uri) => self.request_close_page(uri),
+            None => EventResult::Ignored,
+        }
+    }
+    /// 对选中页面的 runner 执行操作，选中的不是 runner 页面时忽略
+    pub fn with_selected_runner<F>(&mut self, f: F) -> EventResult
+    where
+        F: FnOnce(&mut Runner, &Page),
+    {
+        let page = match self.pages.get(self.selected_page_index) {
+            Some(page) => page.borrow().clone(),
+            None => return EventResult::Ignored,
+        };
+        match page.with_runner(f) {
+            Some(()) => EventResult::Consumed(None),
+            None => EventResult::Ignored,
+        }
+    }
+    /// 在编辑器中打开选中页面光标行对应的源码位置
+    pub fn open_selected_location(&mut self) -> EventResult {
+        let page = match self.pages.get(self.selected_page_index) {
+            Some(page) => page.borrow().clone(),
+            None => return EventResult::Ignored,
+        };
+        let location = match page.selected_location() {
+            Some(location) => location,
+            None => return EventResult::Ignored,
+        };
+        let cwd = page.cwd();
+        EventResult::with_cb(move |s| editor::request(s, &location, cwd.clone()))
+    }
+    /// 移动页面的位置，当前选中的页面保持不变
+    pub fn move_page(&mut self, from: usize, to: usize) -> bool {
+        if from >= self.pages.len() || to >= self.pages.len() {
+            return false;
+        }
+        if from != to {
+            let selected_uri = self.selected_uri();
+            let page = self.pages.remove(from);
+            self.pages.insert(to, page);
+            if let Some(index) = selected_uri.and_then(|uri| self.page_index(&uri)) {
+                self.selected_page_index = index;
+            }
+            self.render_select_page();
+        }
+        true
+    }
+    /// 选中的页面与相邻的页面交换位置，已经在两端时不动
+    pub fn move_selected_page(&mut self, forward: bool) {
+        let from = self.selected_page_index;
+        let to = if forward {
+            from + 1
+        } else {
+            match from.checked_sub(1) {
+                Some(to) => to,
+                None => return,
+            }
+        };
+        self.move_page(from, to);
+    }
+    /// 对指定页面执行操作，页面不存在时返回 None
+    pub fn with_page<F, R>(&mut self, uri: &str, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut Page) -> R,
+    {
+        let index = self.page_index(uri)?;
+        let result = f(&mut self.pages[index].borrow_mut());
+        Some(result)
+    }
+    pub fn page_index(&self, uri: &str) -> Option<usize> {
+        self.pages.iter().position(|page| page.borrow().uri == uri)
+    }
+    pub fn selected_uri(&self) -> Option<String> {
+        self.pages
+            .get(self.selected_page_index)
+            .map(|page| page.borrow().uri.clone())
+    }
+    /// 对选中的页面执行操作，没有页面时返回 None
+    pub fn with_selected_page<F, R>(&mut self, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut Page) -> R,
+    {
+        let page = self.pages.get(self.selected_page_index)?;
+        let result = f(&mut page.borrow_mut());
+        Some(result)
+    }
+    /// 按 tab 栏顺序排列的页面 (uri, 标题)
+    pub fn page_titles(&self) -> Vec<(String, String)> {
+        self.pages
+            .iter()
+            .map(|page| {
+                let page = page.borrow();
+                let title = page.tab.borrow().title().to_owned();
+                (page.uri.clone(), title)
+            })
+            .collect()
+    }
+    /// 选中页面注册的菜单项
+    pub fn menu_entries(&self, section: MenuSection) -> Vec<MenuEntry> {
+        self.pages
+            .get(self.selected_page_index)
+            .map(|page| page.borrow().menu_entries(section))
+            .unwrap_or_default()
+    }
+    /// 客户端创建的页面
+    pub fn pages_of(&self, client: &str) -> Vec<String> {
+        self.pages
+            .iter()
+            .map(|page| page.borrow())
+            .filter(|page| page.owner().as_deref() == Some(client))
+            .map(|page| page.uri.clone())
+            .collect()
+    }
+    /// 客户端通过指定的连接接入，重新接入时清除它的页面上的断开状态
+    pub fn attach_client(&mut self, client: &str, connection: usize) {
+        self.clients.insert(client.to_owned(), connection);
+        for uri in self.pages_of(client) {
+            self.with_page(&uri, |page| {
+                page.tab
+                    .borrow_mut()
+                    .del_status(TabStatus::Warn, DISCONNECTED_REASON.to_owned())
+            });
+        }
+    }
+    /// 把客户端的页面转交给另一个客户端，并且不再记录原来的客户端
+    pub fn transfer_client(&mut self, from: &str, to: &str) {
+        if from == to {
+            return;
+        }
+        self.clients.remove(from);
+        for uri in self.pages_of(from) {
+            self.with_page(&uri, |page| page.set_owner(Some(to.to_owned())));
+        }
+        for page in self.pages.iter() {
+            page.borrow_mut().transfer_client_status(from, to);
+        }
+    }
+    /// 客户端的连接断开：关闭它的页面，或者保留页面并标记为断开，等待它重新接入
+    pub fn detach_client(&mut self, client: &str, connection: usize, close_pages: bool) {
+        if self.clients.get(client) != Some(&connection) {
+            return;
+        }
+        self.clients.remove(client);
+        // 客户端不会再结束它开始的任务，runner 与解析器的状态不受影响
+        for page in self.pages.iter() {
+            page.borrow_mut()
+                .clear_client_status(client, TabStatus::Loading);
+        }
+        for uri in self.pages_of(client) {
+            if close_pages {
+                self.del_page(uri);
+            } else {
+                self.with_page(&uri, |page| {
+                    page.set_busy(false);
+                    page.tab
+                        .borrow_mut()
+                        .add_status(TabStatus::Warn, DISCONNECTED_REASON.to_owned());
+                });
+            }
+        }
+    }
+
+    /// tab 栏与 pages 共用同一份顺序，每次变动后都从 pages 重新同步
+    fn sync_tabbar(&mut self) {
+        let tabs = self
+            .pages
+            .iter()
+            .map(|page| page.borrow().tab.clone())
+            .collect();
+        let selected = if self.pages.is_empty() {
+            None
+        } else {
+            Some(min(self.selected_page_index, self.pages.len() - 1))
+        };
+        self.with_tabbar_mut(|bar| {
+            bar.set_tabs(tabs);
+            bar.set_selected(selected);
+        });
+        let selected_tab = selected.map(|index| self.pages[index].borrow().tab.clone());
+        self.view_status.set_tab(selected_tab);
+    }
 
     fn render_select_page(&mut self) {
+        self.sync_tabbar();
         let pages_count = self.pages.len();
         if pages_count == 0 {
+            self.selected_page_index = 0;
             self.with_content_mut(|layout| {
-                while layout.len() > 0 {
+                while !layout.is_empty() {
                     layout.remove_child(0);
                 }
             });
@@ -132,45 +394,89 @@ impl Browser {
             if self.selected_page_index > pages_count - 1 {
                 self.selected_page_index = pages_count - 1;
             }
-            let selected_index = self.selected_page_index % pages_count;
-            // let z = self.pages.iter().enumerate().cloned();
-            let mut selected_page: Option<Page> = None;
-            {
-                for (i, (_, page)) in self.pages.iter().enumerate() {
-                    if i == selected_index {
-                        selected_page = Some(page.clone().borrow().clone());
-                        break;
-                    }
+            let page = self.pages[self.selected_page_index].borrow().clone();
+            self.with_content_mut(|layout| {
+                while !layout.is_empty() {
+                    layout.remove_child(0);
                 }
-            }
-            if let Some(page) = selected_page {
-                self.with_content_mut(|layout| {
-                    while layout.len() > 0 {
-                        layout.remove_child(0);
-                    }
-                    layout.add_child(page);
-                });
-            }
+                layout.add_child(page);
+            });
         }
     }
 
     pub fn select_page(&mut self, uri: String) {
-        if let Some(select_page) = self.pages.get(&uri) {
-            for (index, (key, _)) in self.pages.iter().enumerate() {
-                if key == &uri {
-                    self.selected_page_index = index;
-                    self.render_select_page();
-                    break;
-                }
-            }
+        if let Some(index) = self.page_index(&uri) {
+            self.selected_page_index = index;
+            self.render_select_page();
         }
     }
     pub fn select_page_by_index(&mut self, index: usize) {
-        if self.pages.len() > 0 {
+        if !self.pages.is_empty() {
             self.selected_page_index = index % self.pages.len();
             self.render_select_page();
         }
     }
+    pub fn select_next_page(&mut self) {
+        self.select_page_by_index(self.selected_page_index + 1);
+    }
+    pub fn select_prev_page(&mut self) {
+        if !self.pages.is_empty() {
+            self.select_page_by_index(self.selected_page_index + self.pages.len() - 1);
+        }
+    }
+    /// tab 栏上的鼠标事件：左键选中，中键关闭，滚轮切换；x 是相对于 tab 栏的坐标
+    fn on_tabbar_mouse_event(&mut self, x: usize, event: MouseEvent) -> EventResult {
+        match event {
+            MouseEvent::Press(MouseButton::Left) => match self.with_tabbar(|bar| bar.hit_test(x)) {
+                Some(TabBarHit::Tab(index)) => self.select_page_by_index(index),
+                Some(TabBarHit::ScrollLeft) => self.select_prev_page(),
+                Some(TabBarHit::ScrollRight) => self.select_next_page(),
+                None => return EventResult::Ignored,
+            },
+            MouseEvent::Press(MouseButton::Middle) => {
+                if let Some(TabBarHit::Tab(index)) = self.with_tabbar(|bar| bar.hit_test(x)) {
+                    let uri = self.pages[index].borrow().uri.clone();
+                    return self.request_close_page(uri);
+                }
+            }
+            MouseEvent::WheelUp => self.select_prev_page(),
+            MouseEvent::WheelDown => self.select_next_page(),
+            _ => return EventResult::Ignored,
+        }
+        EventResult::Consumed(None)
+    }
+    /// 页面内容没有处理的按键：数字键选中对应的页面，i 查看状态，Enter 打开光标行的源码位置
+    fn on_page_key(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Char(c @ '1'..='9') => {
+                let index = c as usize - '1' as usize;
+                if index >= self.pages.len() {
+                    return EventResult::Ignored;
+                }
+                self.select_page_by_index(index);
+                EventResult::Consumed(None)
+            }
+            Event::Char('i') => self.inspect_selected_page(),
+            Event::Key(Key::Enter) => self.open_selected_location(),
+            _ => EventResult::Ignored,
+        }
+    }
+    /// 从当前页面开始，循环查找下一个（或上一个）带有指定状态的页面
+    pub fn select_page_with_status(&mut self, status: TabStatus, forward: bool) -> bool {
+        let count = self.pages.len();
+        for step in 1..=count {
+            let index = if forward {
+                (self.selected_page_index + step) % count
+            } else {
+                (self.selected_page_index + count * step - step) % count
+            };
+            if self.pages[index].borrow().tab.borrow().has_status(&status) {
+                self.select_page_by_index(index);
+                return true;
+            }
+        }
+        false
+    }
 }
 impl View for Browser {
     fn draw(&self, printer: &Printer) {
@@ -181,7 +487,59 @@ impl View for Browser {
     }
 
     fn on_event(&mut self, ch: Event) -> EventResult {
-        self.view.on_event(ch)
+        match ch {
+            Event::Key(Key::Left) | Event::CtrlShift(Key::Tab) => self.select_prev_page(),
+            Event::Key(Key::Right) | Event::Ctrl(Key::Tab) => self.select_next_page(),
+            // 把选中的页面向左/向右移动一格
+            Event::Alt(Key::Left) => self.move_selected_page(false),
+            Event::Alt(Key::Right) => self.move_selected_page(true),
+            // 没有修饰键的按键先交给页面内容，内容不处理时才切换页面、查看状态或打开源码位置
+            Event::Char('1'..='9') | Event::Char('i') | Event::Key(Key::Enter) => {
+                return match self.view_content.on_event(ch.clone()) {
+                    EventResult::Ignored => self.on_page_key(ch),
+                    result => result,
+                };
+            }
+            // 跳到下一个/上一个出错的页面
+            Event::Key(Key::F8) => {
+                self.select_page_with_status(TabStatus::Error, true);
+            }
+            Event::Shift(Key::F8) => {
+                self.select_page_with_status(TabStatus::Error, false);
+            }
+            Event::CtrlChar('w') => return self.request_close_selected_page(),
+            Event::Key(Key::Esc) => {
+                let browser_id = self.id.clone();
+                return EventResult::with_cb(move |s| menu::open(s, &browser_id));
+            }
+            // runner 页面：重启、停止（SIGTERM）、强制结束（SIGKILL）
+            Event::CtrlChar('r') => {
+                return self.with_selected_runner(|runner, page| runner.start(page))
+            }
+            Event::CtrlChar('t') => return self.with_selected_runner(|runner, _| runner.stop()),
+            Event::CtrlChar('k') => return self.with_selected_runner(|runner, _| runner.kill()),
+            Event::Mouse {
+                offset,
+                position,
+                event,
+            } if position.y == offset.y && position.x >= offset.x => {
+                return self.on_tabbar_mouse_event(position.x - offset.x, event);
+            }
+            // 其余的事件直接交给页面内容，内容区域位于 tab 栏的下一行
+            Event::Mouse {
+                offset,
+                position,
+                event,
+            } => {
+                return self.view_content.on_event(Event::Mouse {
+                    offset: offset + (0, 1),
+                    position,
+                    event,
+                })
+            }
+            _ => return self.view_content.on_event(ch),
+        }
+        EventResult::Consumed(None)
     }
 
     fn layout(&mut self, size: Vec2) {
@@ -255,3 +613,110 @@ impl View for Browser {
 //     }
 //     // fn needs_relayout
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn browser(uris: &[&str]) -> Browser {
+        let mut browser = Browser::new("browser".to_owned());
+        for uri in uris {
+            browser.add_page(uri.to_string());
+        }
+        browser
+    }
+
+    fn order(browser: &Browser) -> Vec<String> {
+        browser
+            .pages
+            .iter()
+            .map(|page| page.borrow().uri.clone())
+            .collect()
+    }
+
+    #[test]
+    fn insert_keeps_tab_order_and_selects_the_new_page() {
+        let mut browser = browser(&["build", "tsc"]);
+        browser.insert_page_at(1, "dev".to_owned());
+        browser.insert_page_at(99, "deps".to_owned());
+        assert_eq!(order(&browser), ["build", "dev", "tsc", "deps"]);
+        assert_eq!(browser.selected_uri().as_deref(), Some("deps"));
+        assert_eq!(browser.page_index("tsc"), Some(2));
+        assert_eq!(browser.page_index("missing"), None);
+        // 已经存在的页面只会被选中，不会重复插入
+        browser.insert_page_at(0, "tsc".to_owned());
+        assert_eq!(order(&browser), ["build", "dev", "tsc", "deps"]);
+        assert_eq!(browser.selected_uri().as_deref(), Some("tsc"));
+    }
+
+    #[test]
+    fn move_keeps_the_selected_page() {
+        let mut browser = browser(&["build", "tsc", "dev", "deps"]);
+        browser.select_page("tsc".to_owned());
+        assert!(browser.move_page(0, 3));
+        assert_eq!(order(&browser), ["tsc", "dev", "deps", "build"]);
+        assert_eq!(browser.selected_uri().as_deref(), Some("tsc"));
+        assert!(browser.move_page(0, 2));
+        assert_eq!(order(&browser), ["dev", "deps", "tsc", "build"]);
+        assert_eq!(browser.selected_uri().as_deref(), Some("tsc"));
+        assert!(!browser.move_page(1, 4));
+        assert_eq!(order(&browser), ["dev", "deps", "tsc", "build"]);
+    }
+
+    #[test]
+    fn deleting_the_selected_page_selects_a_neighbor() {
+        let mut browser = browser(&["build", "tsc", "dev", "deps"]);
+        browser.select_page("tsc".to_owned());
+        browser.del_page("tsc".to_owned());
+        assert_eq!(order(&browser), ["build", "dev", "deps"]);
+        assert_eq!(browser.selected_uri().as_deref(), Some("dev"));
+        // 最后一个页面被关闭时回退到左侧的邻居
+        browser.select_page("deps".to_owned());
+        browser.del_page("deps".to_owned());
+        assert_eq!(browser.selected_uri().as_deref(), Some("dev"));
+        browser.del_page("dev".to_owned());
+        browser.del_page("build".to_owned());
+        assert_eq!(browser.selected_uri(), None);
+    }
+
+    #[test]
+    fn deleting_another_page_keeps_the_selection() {
+        let mut browser = browser(&["build", "tsc", "dev"]);
+        browser.select_page("dev".to_owned());
+        browser.del_page("build".to_owned());
+        assert_eq!(browser.selected_uri().as_deref(), Some("dev"));
+        browser.del_page("missing".to_owned());
+        assert_eq!(order(&browser), ["tsc", "dev"]);
+    }
+
+    #[test]
+    fn digits_select_pages_when_the_content_ignores_them() {
+        let mut browser = browser(&["build", "tsc", "dev"]);
+        assert!(matches!(
+            browser.on_event(Event::Char('2')),
+            EventResult::Consumed(_)
+        ));
+        assert_eq!(browser.selected_uri().as_deref(), Some("tsc"));
+        assert!(matches!(
+            browser.on_event(Event::Char('9')),
+            EventResult::Ignored
+        ));
+        assert_eq!(browser.selected_uri().as_deref(), Some("tsc"));
+        // 没有光标行时 Enter 没有可以打开的位置
+        assert!(matches!(
+            browser.on_event(Event::Key(Key::Enter)),
+            EventResult::Ignored
+        ));
+    }
+
+    #[test]
+    fn alt_arrows_move_the_selected_page() {
+        let mut browser = browser(&["build", "tsc", "dev"]);
+        browser.select_page("build".to_owned());
+        browser.on_event(Event::Alt(Key::Left));
+        assert_eq!(order(&browser), ["build", "tsc", "dev"]);
+        browser.on_event(Event::Alt(Key::Right));
+        assert_eq!(order(&browser), ["tsc", "build", "dev"]);
+        assert_eq!(browser.selected_uri().as_deref(), Some("build"));
+    }
+}
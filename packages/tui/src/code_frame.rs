@@ -0,0 +1,255 @@
+use crate::diagnostic::Location;
+
+use cursive::theme::{BaseColor, ColorStyle, Effect, Style};
+use cursive::view::View;
+use cursive::{Printer, Vec2};
+
+use std::path::Path;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// 诊断位置上下各显示的行数
+const CONTEXT_LINES: usize = 2;
+/// 源码中的 tab 展开为空格的宽度
+const TAB_WIDTH: usize = 4;
+
+/// 源码位置的预览，类似 tsc `--pretty` 输出中的代码帧：
+/// 显示位置前后几行带行号的源码，并在出错的列下面画出波浪线
+pub struct CodeFrame {
+    location: Location,
+    /// 上下文中的行（行号，展开 tab 后的内容），读取失败时是错误消息
+    lines: Result<Vec<(usize, String)>, String>,
+    /// 波浪线在出错行中的起始位置与宽度，按显示宽度计算
+    underline: (usize, usize),
+}
+
+impl CodeFrame {
+    /// 读取文件中位置附近的内容，相对路径相对于 cwd
+    pub fn load(location: Location, cwd: Option<&Path>) -> Self {
+        let path = match cwd {
+            Some(cwd) => cwd.join(&location.file),
+            None => Path::new(&location.file).to_path_buf(),
+        };
+        let mut underline = (0, 1);
+        let lines = match std::fs::read(&path) {
+            Ok(bytes) => {
+                let source = String::from_utf8_lossy(&bytes);
+                let count = source.lines().count();
+                if location.line == 0 || location.line > count {
+                    Err(format!(
+                        "line {} is past the end of the file ({} lines)",
+                        location.line, count
+                    ))
+                } else {
+                    let first = location.line.saturating_sub(CONTEXT_LINES).max(1);
+                    Ok(source
+                        .lines()
+                        .enumerate()
+                        .skip(first - 1)
+                        .take(location.line + CONTEXT_LINES + 1 - first)
+                        .map(|(index, line)| {
+                            if index + 1 == location.line {
+                                underline = underline_of(line, location.column);
+                            }
+                            (index + 1, expand_tabs(line))
+                        })
+                        .collect())
+                }
+            }
+            Err(err) => Err(err.to_string()),
+        };
+        CodeFrame {
+            location,
+            lines,
+            underline,
+        }
+    }
+    pub fn location(&self) -> &Location {
+        &self.location
+    }
+    /// 标题行、上下文与波浪线所占的行数
+    pub fn height(&self) -> usize {
+        match &self.lines {
+            Ok(lines) => 1 + lines.len() + 1,
+            Err(_) => 2,
+        }
+    }
+}
+
+impl View for CodeFrame {
+    fn draw(&self, printer: &Printer) {
+        let title = format!("─ {} ", self.location);
+        printer.with_color(ColorStyle::secondary(), |printer| {
+            printer.print_hline((0, 0), printer.size.x, "─");
+            printer.print((0, 0), &title);
+        });
+        let lines = match &self.lines {
+            Ok(lines) => lines,
+            Err(err) => {
+                printer.with_effect(Effect::Dim, |printer| printer.print((1, 1), err));
+                return;
+            }
+        };
+        let number_width = lines
+            .last()
+            .map_or(1, |(number, _)| number.to_string().len());
+        let gutter = number_width + 5;
+        // 出错的列超出宽度时整体向左平移，保证波浪线可见
+        let (start, width) = self.underline;
+        let available = printer.size.x.saturating_sub(gutter).max(1);
+        let shift = (start + width).saturating_sub(available.saturating_sub(1));
+
+        let mut y = 1;
+        for (number, line) in lines {
+            let current = *number == self.location.line;
+            let gutter_text = format!(
+                "{} {:>width$} │ ",
+                if current { '>' } else { ' ' },
+                number,
+                width = number_width
+            );
+            let gutter_style = if current {
+                Style::from(BaseColor::Red.light()).combine(Effect::Bold)
+            } else {
+                Style::from(Effect::Dim)
+            };
+            printer.with_style(gutter_style, |printer| printer.print((0, y), &gutter_text));
+            printer.print((gutter, y), skip_width(line, shift));
+            y += 1;
+            if current {
+                printer.with_effect(Effect::Dim, |printer| {
+                    printer.print(
+                        (0, y),
+                        &format!("  {:>width$} │ ", "", width = number_width),
+                    );
+                });
+                printer.with_style(BaseColor::Red.light(), |printer| {
+                    printer.print_hline(((gutter + start).saturating_sub(shift), y), width, "~");
+                });
+                y += 1;
+            }
+        }
+    }
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        Vec2::new(constraint.x, self.height())
+    }
+}
+
+fn expand_tabs(line: &str) -> String {
+    let mut expanded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = TAB_WIDTH - width % TAB_WIDTH;
+            expanded.push_str(&" ".repeat(spaces));
+            width += spaces;
+        } else {
+            expanded.push(c);
+            width += c.width().unwrap_or(0);
+        }
+    }
+    expanded
+}
+
+/// 列号（从 1 开始，按字符计数）对应的显示位置，以及从这里开始的标识符的显示宽度
+fn underline_of(line: &str, column: usize) -> (usize, usize) {
+    let index = column.saturating_sub(1);
+    let prefix: String = line.chars().take(index).collect();
+    let start = expand_tabs(&prefix).width();
+    let token: String = line
+        .chars()
+        .skip(index)
+        .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '$')
+        .collect();
+    (start, token.width().max(1))
+}
+
+/// 跳过开头指定显示宽度的内容
+fn skip_width(line: &str, width: usize) -> &str {
+    let mut skipped = 0;
+    for (index, c) in line.char_indices() {
+        if skipped >= width {
+            return &line[index..];
+        }
+        skipped += c.width().unwrap_or(0);
+    }
+    ""
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 在临时目录中写入源码文件，再按相对路径加载其中位置的代码帧
+    fn load(name: &str, source: &str, line: usize, column: usize) -> CodeFrame {
+        let dir = std::env::temp_dir().join(format!("code-frame-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(name), source).unwrap();
+        let location = Location {
+            file: name.to_owned(),
+            line,
+            column,
+        };
+        CodeFrame::load(location, Some(&dir))
+    }
+
+    #[test]
+    fn expands_tabs_to_the_next_stop() {
+        assert_eq!(expand_tabs("\tx"), "    x");
+        assert_eq!(expand_tabs("ab\tc"), "ab  c");
+        assert_eq!(expand_tabs("中\tx"), "中  x");
+        assert_eq!(expand_tabs("abcd\t"), "abcd    ");
+    }
+
+    #[test]
+    fn underlines_the_identifier_at_the_column() {
+        assert_eq!(underline_of("const a b = 1", 9), (8, 1));
+        assert_eq!(underline_of("let value = foo_bar$;", 13), (12, 8));
+        // 不是标识符时至少画一个字符
+        assert_eq!(underline_of("a + ;", 5), (4, 1));
+        // tab 与宽字符按显示宽度计算
+        assert_eq!(underline_of("\tx = 中文", 2), (4, 1));
+        assert_eq!(underline_of("s = 中文;", 5), (4, 4));
+        // 列号超出行尾
+        assert_eq!(underline_of("ab", 10), (2, 1));
+    }
+
+    #[test]
+    fn loads_context_around_the_location() {
+        let frame = load("context.ts", "1\n2\n3\n\tconst a b\n5\n6\n7\n", 4, 10);
+        assert_eq!(
+            frame.lines,
+            Ok(vec![
+                (2, "2".to_owned()),
+                (3, "3".to_owned()),
+                (4, "    const a b".to_owned()),
+                (5, "5".to_owned()),
+                (6, "6".to_owned()),
+            ])
+        );
+        assert_eq!(frame.underline, (12, 1));
+        assert_eq!(frame.height(), 7);
+
+        let frame = load("first.ts", "error\nok\n", 1, 1);
+        assert_eq!(
+            frame.lines,
+            Ok(vec![(1, "error".to_owned()), (2, "ok".to_owned())])
+        );
+        assert_eq!(frame.underline, (0, 5));
+    }
+
+    #[test]
+    fn reports_locations_past_the_end() {
+        let frame = load("short.ts", "a\nb\n", 3, 1);
+        assert_eq!(
+            frame.lines,
+            Err("line 3 is past the end of the file (2 lines)".to_owned())
+        );
+        assert_eq!(frame.height(), 2);
+
+        let frame = load("empty.ts", "", 1, 1);
+        assert_eq!(
+            frame.lines,
+            Err("line 1 is past the end of the file (0 lines)".to_owned())
+        );
+    }
+}
@@ -0,0 +1,316 @@
+use crate::page_log::{level_style, step_level};
+use crate::status_bar;
+use crate::utils::format_time;
+
+use cursive::event::Key;
+use cursive::theme::Effect;
+use cursive::view::{Nameable, Resizable, ScrollStrategy, Scrollable, View};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, TextView};
+use cursive::{Cursive, Printer, Vec2, With};
+
+use log::{Level, LevelFilter};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use unicode_width::UnicodeWidthStr;
+
+/// 全局控制台保留的日志条数
+const CONSOLE_LIMIT: usize = 5_000;
+/// 控制台图层与其中视图的名字
+const CONSOLE_LAYER: &str = "console";
+const CONSOLE_VIEW: &str = "console::view";
+const DEFAULT_DUMP_PATH: &str = "pkgm-tui-console.log";
+
+pub struct ConsoleRecord {
+    /// 递增的序号，暂停时只显示序号不超过暂停位置的记录
+    pub seq: u64,
+    pub level: Level,
+    pub target: String,
+    pub time: SystemTime,
+    pub message: String,
+}
+
+impl ConsoleRecord {
+    /// `HH:MM:SS.mmm`
+    fn timestamp(&self) -> String {
+        let millis = self
+            .time
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.subsec_millis());
+        format!("{}.{:03}", format_time(self.time), millis)
+    }
+}
+
+struct ConsoleLogs {
+    records: VecDeque<ConsoleRecord>,
+    next_seq: u64,
+}
+
+lazy_static! {
+    static ref LOGS: Mutex<ConsoleLogs> = Mutex::new(ConsoleLogs {
+        records: VecDeque::new(),
+        next_seq: 0,
+    });
+}
+
+/// 记录一条日志，所有页面与内部模块的日志都会进入控制台
+pub fn push(record: &log::Record) {
+    let mut logs = LOGS.lock().unwrap();
+    if logs.records.len() >= CONSOLE_LIMIT {
+        logs.records.pop_front();
+    }
+    let seq = logs.next_seq;
+    logs.next_seq += 1;
+    logs.records.push_back(ConsoleRecord {
+        seq,
+        level: record.level(),
+        target: record.target().to_owned(),
+        time: SystemTime::now(),
+        message: record.args().to_string(),
+    });
+}
+
+/// 控制台的过滤条件
+#[derive(Clone)]
+struct ConsoleFilter {
+    level: LevelFilter,
+    /// target 中包含的文本，例如 `page::tsc`、`server`
+    target: String,
+    /// 消息中包含的文本，忽略大小写
+    search: String,
+    /// 暂停时最后一条记录的序号
+    paused_at: Option<u64>,
+}
+
+impl ConsoleFilter {
+    fn matches(&self, record: &ConsoleRecord) -> bool {
+        record.level <= self.level
+            && self.paused_at.is_none_or(|seq| record.seq <= seq)
+            && record.target.contains(&self.target)
+            && (self.search.is_empty()
+                || record
+                    .message
+                    .to_lowercase()
+                    .contains(&self.search.to_lowercase()))
+    }
+}
+
+/// 按过滤条件显示全局日志，每条记录显示为一行：时间、级别、target、消息
+struct ConsoleView {
+    filter: ConsoleFilter,
+}
+
+impl ConsoleView {
+    fn new() -> Self {
+        ConsoleView {
+            filter: ConsoleFilter {
+                level: LevelFilter::Trace,
+                target: String::new(),
+                search: String::new(),
+                paused_at: None,
+            },
+        }
+    }
+    fn count(&self) -> usize {
+        let logs = LOGS.lock().unwrap();
+        logs.records
+            .iter()
+            .filter(|record| self.filter.matches(record))
+            .count()
+    }
+    fn toggle_pause(&mut self) {
+        self.filter.paused_at = match self.filter.paused_at {
+            Some(_) => None,
+            None => LOGS.lock().unwrap().next_seq.checked_sub(1),
+        };
+    }
+    fn is_paused(&self) -> bool {
+        self.filter.paused_at.is_some()
+    }
+    /// 把符合过滤条件的记录写入文件，返回写入的条数
+    fn dump(&self, path: &str) -> io::Result<usize> {
+        let mut file = File::create(path)?;
+        let logs = LOGS.lock().unwrap();
+        let mut count = 0;
+        for record in logs.records.iter().filter(|r| self.filter.matches(r)) {
+            writeln!(
+                file,
+                "{} {:<5} {} {}",
+                record.timestamp(),
+                record.level,
+                record.target,
+                record.message
+            )?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+impl View for ConsoleView {
+    fn draw(&self, printer: &Printer) {
+        let logs = LOGS.lock().unwrap();
+        let records = logs
+            .records
+            .iter()
+            .filter(|record| self.filter.matches(record));
+        // 只绘制滚动区域中可见的行
+        let first = printer.content_offset.y;
+        for (y, record) in records.enumerate().skip(first).take(printer.output_size.y) {
+            let timestamp = record.timestamp();
+            printer.with_effect(Effect::Dim, |printer| printer.print((0, y), &timestamp));
+            let x = timestamp.len() + 1;
+            let level = format!("{:<5}", record.level);
+            printer.with_style(level_style(record.level), |printer| {
+                printer.print((x, y), &level)
+            });
+            let x = x + level.len() + 1;
+            printer.with_effect(Effect::Bold, |printer| {
+                printer.print((x, y), &record.target)
+            });
+            let x = x + record.target.width() + 1;
+            printer.print((x, y), record.message.lines().next().unwrap_or_default());
+        }
+    }
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        Vec2::new(constraint.x, self.count())
+    }
+}
+
+fn with_console<F, R>(s: &mut Cursive, f: F) -> Option<R>
+where
+    F: FnOnce(&mut ConsoleView) -> R,
+{
+    s.call_on_name(CONSOLE_VIEW, f)
+}
+
+/// 按钮的文字随状态变化
+fn level_label(level: LevelFilter) -> String {
+    format!("Level: {}", level)
+}
+fn pause_label(paused: bool) -> &'static str {
+    if paused {
+        "Resume"
+    } else {
+        "Pause"
+    }
+}
+
+fn set_button_label(s: &mut Cursive, index: usize, label: String) {
+    s.call_on_name(CONSOLE_LAYER, |dialog: &mut Dialog| {
+        if let Some(button) = dialog.buttons_mut().nth(index) {
+            button.set_label(label);
+        }
+    });
+}
+
+fn cycle_level(s: &mut Cursive) {
+    let level = with_console(s, |console| {
+        // 依次降低显示的级别，Error 之后回到 Trace；Off 没有意义
+        console.filter.level = match console.filter.level {
+            LevelFilter::Error => LevelFilter::Trace,
+            level => step_level(level, false),
+        };
+        console.filter.level
+    });
+    if let Some(level) = level {
+        set_button_label(s, 0, level_label(level));
+    }
+}
+
+fn toggle_pause(s: &mut Cursive) {
+    if let Some(paused) = with_console(s, |console| {
+        console.toggle_pause();
+        console.is_paused()
+    }) {
+        set_button_label(s, 1, pause_label(paused).to_owned());
+    }
+}
+
+fn clear(_: &mut Cursive) {
+    LOGS.lock().unwrap().records.clear();
+}
+
+fn ask_dump_path(s: &mut Cursive) {
+    let save = |s: &mut Cursive, path: &str| {
+        s.pop_layer();
+        let path = path.to_owned();
+        match with_console(s, |console| console.dump(&path)) {
+            Some(Ok(count)) => {
+                status_bar::set_msg(Some(format!("saved {} records to {}", count, path)), false)
+            }
+            Some(Err(err)) => {
+                s.add_layer(Dialog::info(format!("Failed to write {}: {}", path, err)))
+            }
+            None => {}
+        }
+    };
+    s.add_layer(
+        Dialog::around(
+            EditView::new()
+                .content(DEFAULT_DUMP_PATH)
+                .on_submit(save)
+                .with_name("console::path")
+                .min_width(40),
+        )
+        .title("Save console to")
+        .button("Save", move |s| {
+            let path = s
+                .call_on_name("console::path", |edit: &mut EditView| edit.get_content())
+                .unwrap_or_default();
+            save(s, &path);
+        })
+        .dismiss_button("Cancel"),
+    );
+}
+
+/// 打开或者关闭全局控制台，打开时 Esc 也可以关闭它
+pub fn toggle(s: &mut Cursive) {
+    if s.find_name::<Dialog>(CONSOLE_LAYER).is_some() {
+        if let Some(position) = s.screen_mut().find_layer_from_name(CONSOLE_LAYER) {
+            s.screen_mut().remove_layer(position);
+        }
+        return;
+    }
+    let filters = LinearLayout::horizontal()
+        .child(TextView::new("Target "))
+        .child(
+            EditView::new()
+                .on_edit(|s, text, _| {
+                    with_console(s, |console| console.filter.target = text.to_owned());
+                })
+                .full_width(),
+        )
+        .child(TextView::new(" Search "))
+        .child(
+            EditView::new()
+                .on_edit(|s, text, _| {
+                    with_console(s, |console| console.filter.search = text.to_owned());
+                })
+                .full_width(),
+        );
+    let console = ConsoleView::new();
+    let level = console.filter.level;
+    let content = LinearLayout::vertical().child(filters).child(
+        console
+            .with_name(CONSOLE_VIEW)
+            .scrollable()
+            .scroll_strategy(ScrollStrategy::StickToBottom)
+            .full_screen(),
+    );
+    s.add_layer(
+        Dialog::around(content)
+            .title("Console")
+            .button(level_label(level), cycle_level)
+            .button(pause_label(false), toggle_pause)
+            .button("Clear", clear)
+            .button("Save…", ask_dump_path)
+            .button("Close", toggle)
+            .with_name(CONSOLE_LAYER)
+            .wrap_with(OnEventView::new)
+            .on_event(Key::Esc, toggle)
+            .full_screen(),
+    );
+}
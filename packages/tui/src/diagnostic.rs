@@ -0,0 +1,141 @@
+use regex::Regex;
+use std::fmt;
+
+lazy_static! {
+    /// tsc 的 `src/a.ts(1,7)`
+    static ref PAREN_LOCATION: Regex =
+        Regex::new(r"((?:[\w.@~-]*/)*[\w.@-]+\.\w+)\((\d+),(\d+)\)").unwrap();
+    /// `src/a.ts:1:7`、`/abs/b.vue:3`，文件名需要带扩展名，避免把时间之类的内容当成位置
+    static ref COLON_LOCATION: Regex =
+        Regex::new(r"((?:[\w.@~-]*/)*[\w.@-]+\.\w+):(\d+)(?::(\d+))?").unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+    Message,
+}
+
+impl Severity {
+    pub fn parse(text: &str) -> Option<Severity> {
+        match text {
+            "error" => Some(Severity::Error),
+            "warning" => Some(Severity::Warning),
+            "message" => Some(Severity::Message),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Message => "message",
+        })
+    }
+}
+
+/// 从编译器输出中解析出来的一条诊断信息，行号与列号从 1 开始
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub severity: Severity,
+    /// 例如 `TS2322`
+    pub code: Option<String>,
+    /// 多行的消息用 `\n` 连接
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// `file:line:column`，编辑器与终端都能识别的位置格式
+    pub fn location(&self) -> String {
+        format!("{}:{}:{}", self.file, self.line, self.column)
+    }
+    pub fn position(&self) -> Location {
+        Location {
+            file: self.file.clone(),
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+/// 源码中的位置，行号与列号从 1 开始
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    /// 在一行输出中查找第一个源码位置
+    pub fn find(text: &str) -> Option<Location> {
+        let captures = PAREN_LOCATION.captures(text).or_else(|| {
+            // URL 中的主机与端口（例如 vite 的 `http://127.0.0.1:5173/`）不是源码位置
+            COLON_LOCATION
+                .captures_iter(text)
+                .find(|captures| !captures[1].starts_with("//"))
+        })?;
+        Some(Location {
+            file: captures[1].to_owned(),
+            line: captures[2].parse().ok()?,
+            column: captures
+                .get(3)
+                .and_then(|column| column.as_str().parse().ok())
+                .unwrap_or(1),
+        })
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - {}", self.location(), self.severity)?;
+        if let Some(code) = &self.code {
+            write!(f, " {}", code)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(text: &str) -> Option<(String, usize, usize)> {
+        Location::find(text).map(|location| (location.file, location.line, location.column))
+    }
+
+    #[test]
+    fn finds_tsc_and_colon_locations() {
+        assert_eq!(
+            find("src/a.ts(3,5): error TS2322: Type"),
+            Some(("src/a.ts".to_owned(), 3, 5))
+        );
+        assert_eq!(
+            find("src/b.ts:7:1 - error TS2304"),
+            Some(("src/b.ts".to_owned(), 7, 1))
+        );
+        assert_eq!(find("/abs/c.vue:3"), Some(("/abs/c.vue".to_owned(), 3, 1)));
+    }
+
+    #[test]
+    fn ignores_urls() {
+        assert_eq!(find("  ➜  Local:   http://127.0.0.1:5173/"), None);
+        assert_eq!(
+            find("see http://127.0.0.1:5173/ for src/main.ts:2:4"),
+            Some(("src/main.ts".to_owned(), 2, 4))
+        );
+    }
+}
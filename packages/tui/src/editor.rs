@@ -0,0 +1,91 @@
+use crate::diagnostic::Location;
+
+use cursive::Cursive;
+
+use std::fs::OpenOptions;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::Mutex;
+
+/// 没有配置编辑器，也没有 `$EDITOR` 时使用
+const FALLBACK_EDITOR: &str = "vi";
+
+lazy_static! {
+    /// `--editor` 指定的命令模板
+    static ref TEMPLATE: Mutex<Option<String>> = Mutex::new(None);
+    /// 等待 cursive 暂停后启动的编辑器
+    static ref PENDING: Mutex<Option<EditorLaunch>> = Mutex::new(None);
+}
+
+/// 设置打开源码位置的命令模板，例如 `code -g {file}:{line}:{col}`；
+/// 没有设置时使用 `$EDITOR +{line} {file}`
+pub fn init(template: Option<String>) {
+    *TEMPLATE.lock().unwrap() = template;
+}
+
+/// 请求在编辑器中打开源码位置：先退出 cursive 的事件循环，把终端交给编辑器，
+/// 由 main 中的循环启动编辑器，结束后再恢复界面
+pub fn request(s: &mut Cursive, location: &Location, cwd: Option<PathBuf>) {
+    *PENDING.lock().unwrap() = Some(EditorLaunch::new(location, cwd));
+    s.quit();
+}
+
+pub fn take_pending() -> Option<EditorLaunch> {
+    PENDING.lock().unwrap().take()
+}
+
+/// 打开编辑器的命令，cwd 是输出这个位置的 runner 的工作目录，相对路径相对于它
+pub struct EditorLaunch {
+    program: String,
+    args: Vec<String>,
+    cwd: Option<PathBuf>,
+}
+
+impl EditorLaunch {
+    fn new(location: &Location, cwd: Option<PathBuf>) -> Self {
+        let template = TEMPLATE.lock().unwrap().clone().unwrap_or_else(|| {
+            let editor = std::env::var("EDITOR")
+                .ok()
+                .filter(|editor| !editor.trim().is_empty())
+                .unwrap_or_else(|| FALLBACK_EDITOR.to_owned());
+            format!("{} +{{line}} {{file}}", editor)
+        });
+        let mut words = template.split_whitespace().map(|word| {
+            word.replace("{file}", &location.file)
+                .replace("{line}", &location.line.to_string())
+                .replace("{col}", &location.column.to_string())
+        });
+        let program = words.next().unwrap_or_else(|| FALLBACK_EDITOR.to_owned());
+        let mut args: Vec<String> = words.collect();
+        if !template.contains("{file}") {
+            args.push(location.file.clone());
+        }
+        EditorLaunch { program, args, cwd }
+    }
+    pub fn command_line(&self) -> String {
+        let mut line = self.program.clone();
+        for arg in &self.args {
+            line.push(' ');
+            line.push_str(arg);
+        }
+        line
+    }
+    /// 在当前终端中运行编辑器并等待它退出；stdin 是控制协议的管道时，
+    /// 编辑器改为直接使用 `/dev/tty`，避免与读取指令的线程争抢输入
+    pub fn run(&self) -> io::Result<ExitStatus> {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+        if !io::stdin().is_terminal() {
+            let tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+            command
+                .stdin(Stdio::from(tty.try_clone()?))
+                .stdout(Stdio::from(tty.try_clone()?))
+                .stderr(Stdio::from(tty));
+        }
+        command.status()
+    }
+}
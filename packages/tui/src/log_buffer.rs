@@ -0,0 +1,346 @@
+use crate::ansi::{AnsiAction, AnsiParser, EraseMode, MAX_CSI_ARG};
+use cursive::theme::{BaseColor, Style};
+use cursive::utils::markup::StyledString;
+use std::collections::VecDeque;
+
+/// 默认的回滚行数上限，超出后丢弃最早的行
+pub const DEFAULT_SCROLLBACK: usize = 10_000;
+const TAB_WIDTH: usize = 8;
+
+/// 只追加的日志缓冲区，按行存储，写入时不需要重新排版已有的内容。
+/// 写入的文本会经过 ANSI 解析：SGR 转为样式，`\r`、擦除行、光标上移、清屏等指令
+/// 会像终端一样改写已有的行，这样 vite 之类的进度输出不会留下一堆残影
+pub struct LogBuffer {
+    lines: VecDeque<StyledString>,
+    parser: AnsiParser,
+    /// 带基础样式写入（例如 stderr）的内容使用独立的解析器，两边的 SGR 状态互不影响
+    styled_parser: AnsiParser,
+    /// 光标所在的行；可以等于 lines.len()，表示换行后还没有写入内容的新行
+    cursor_row: usize,
+    /// 光标所在的列，按字符计数
+    cursor_col: usize,
+    /// 最近一次清屏后第一行的位置，`ESC[row;colH` 相对于它定位
+    screen_top: usize,
+    /// 因超出回滚上限或清屏而丢弃的行数，视图用它来保持滚动位置稳定
+    dropped: usize,
+    limit: usize,
+    /// 连续相同的行合并为一行，并在末尾显示 `(xN)`，与 TS 版本 `$viteSameCount` 的效果一致
+    collapse_duplicates: bool,
+    /// 最近一个完成的行的原始内容与重复次数
+    last_line: Option<(StyledString, usize)>,
+}
+
+impl LogBuffer {
+    pub fn new() -> Self {
+        Self::with_limit(DEFAULT_SCROLLBACK)
+    }
+    pub fn with_limit(limit: usize) -> Self {
+        LogBuffer {
+            lines: VecDeque::new(),
+            parser: AnsiParser::new(),
+            styled_parser: AnsiParser::new(),
+            cursor_row: 0,
+            cursor_col: 0,
+            screen_top: 0,
+            dropped: 0,
+            limit: limit.max(1),
+            collapse_duplicates: false,
+            last_line: None,
+        }
+    }
+
+    pub fn write(&mut self, text: &str) {
+        for action in self.parser.parse(text) {
+            self.apply(action);
+        }
+        self.trim();
+    }
+    /// 以基础样式写入：文本自身没有设置的颜色与效果使用基础样式，例如把 stderr 显示为红色
+    pub fn write_styled(&mut self, text: &str, base: Style) {
+        for action in self.styled_parser.parse(text) {
+            match action {
+                AnsiAction::Text(text, style) => self.put_text(&text, base.combine(style)),
+                AnsiAction::Tab(style) => self.put_tab(base.combine(style)),
+                action => self.apply(action),
+            }
+        }
+        self.trim();
+    }
+
+    fn apply(&mut self, action: AnsiAction) {
+        match action {
+            AnsiAction::Text(text, style) => self.put_text(&text, style),
+            AnsiAction::NewLine => {
+                if self.collapse_duplicates {
+                    self.collapse_line();
+                }
+                self.cursor_row += 1;
+                self.cursor_col = 0;
+            }
+            AnsiAction::CarriageReturn => self.cursor_col = 0,
+            AnsiAction::Tab(style) => self.put_tab(style),
+            AnsiAction::CursorUp(n) => {
+                self.move_cursor(self.cursor_row.saturating_sub(n), self.cursor_col)
+            }
+            AnsiAction::CursorDown(n) => {
+                self.move_cursor(self.cursor_row.saturating_add(n), self.cursor_col)
+            }
+            AnsiAction::CursorForward(n) => {
+                self.move_cursor(self.cursor_row, self.cursor_col.saturating_add(n))
+            }
+            AnsiAction::CursorBack(n) => self.cursor_col = self.cursor_col.saturating_sub(n),
+            AnsiAction::CursorColumn(col) => self.move_cursor(self.cursor_row, col),
+            AnsiAction::CursorPosition(row, col) => {
+                self.move_cursor(self.screen_top.saturating_add(row), col)
+            }
+            AnsiAction::EraseLine(mode) => self.erase_line(mode),
+            AnsiAction::EraseDisplay(mode) => match mode {
+                EraseMode::All => self.clear(),
+                EraseMode::ToEnd => {
+                    self.erase_line(EraseMode::ToEnd);
+                    self.lines.truncate(self.cursor_row + 1);
+                }
+                EraseMode::ToStart => {
+                    self.erase_line(EraseMode::ToStart);
+                    for row in 0..self.cursor_row.min(self.lines.len()) {
+                        self.lines[row] = StyledString::new();
+                    }
+                }
+            },
+        }
+    }
+
+    /// 移动光标，最多移动到已有的行或者所在行的末尾之外 MAX_CSI_ARG 的位置，
+    /// 这样多个连续的光标指令也不会让下一次写入补出大量的空行与空格
+    fn move_cursor(&mut self, row: usize, col: usize) {
+        self.cursor_row = row.min(self.lines.len() + MAX_CSI_ARG);
+        let line_len = self
+            .lines
+            .get(self.cursor_row)
+            .map_or(0, |line| line.source().chars().count());
+        self.cursor_col = col.min(line_len + MAX_CSI_ARG);
+    }
+
+    /// 在光标处写入文本：光标在行尾时直接追加，否则覆盖已有的字符
+    fn put_text(&mut self, text: &str, style: Style) {
+        while self.lines.len() <= self.cursor_row {
+            self.lines.push_back(StyledString::new());
+        }
+        let line = &mut self.lines[self.cursor_row];
+        let line_len = line.source().chars().count();
+        let text_len = text.chars().count();
+        if self.cursor_col >= line_len {
+            line.append_plain(" ".repeat(self.cursor_col - line_len));
+            line.append_styled(text, style);
+        } else {
+            let mut cells = to_cells(line);
+            let end = self.cursor_col + text_len;
+            if cells.len() < end {
+                cells.resize(end, (' ', Style::none()));
+            }
+            for (i, c) in text.chars().enumerate() {
+                cells[self.cursor_col + i] = (c, style);
+            }
+            *line = from_cells(&cells);
+        }
+        self.cursor_col += text_len;
+    }
+
+    /// 用空格补齐到下一个制表位
+    fn put_tab(&mut self, style: Style) {
+        let spaces = TAB_WIDTH - self.cursor_col % TAB_WIDTH;
+        self.put_text(&" ".repeat(spaces), style);
+    }
+
+    fn erase_line(&mut self, mode: EraseMode) {
+        let line = match self.lines.get_mut(self.cursor_row) {
+            Some(line) => line,
+            None => return,
+        };
+        match mode {
+            EraseMode::All => *line = StyledString::new(),
+            EraseMode::ToEnd => {
+                let cells = to_cells(line);
+                if cells.len() > self.cursor_col {
+                    *line = from_cells(&cells[..self.cursor_col]);
+                }
+            }
+            EraseMode::ToStart => {
+                let mut cells = to_cells(line);
+                let end = (self.cursor_col + 1).min(cells.len());
+                for cell in cells[..end].iter_mut() {
+                    *cell = (' ', Style::none());
+                }
+                *line = from_cells(&cells);
+            }
+        }
+    }
+
+    pub fn set_collapse_duplicates(&mut self, collapse: bool) {
+        self.collapse_duplicates = collapse;
+        self.last_line = None;
+    }
+    /// 光标所在的行刚刚结束：与上一行相同时移除它，改为更新上一行的重复次数
+    fn collapse_line(&mut self) {
+        let row = self.cursor_row;
+        // 只处理追加在末尾的行，被光标指令改写的行不参与合并
+        if row + 1 != self.lines.len() {
+            self.last_line = None;
+            return;
+        }
+        let line = &self.lines[row];
+        if line.source().trim().is_empty() {
+            self.last_line = None;
+            return;
+        }
+        match &mut self.last_line {
+            Some((last, count)) if row > 0 && last.source() == line.source() => {
+                *count += 1;
+                let mut collapsed = last.clone();
+                collapsed.append_styled(
+                    format!(" (x{})", count),
+                    Style::from(BaseColor::Yellow.light()),
+                );
+                self.lines.pop_back();
+                self.lines[row - 1] = collapsed;
+                self.cursor_row -= 1;
+            }
+            _ => self.last_line = Some((line.clone(), 1)),
+        }
+    }
+
+    fn trim(&mut self) {
+        while self.lines.len() > self.limit {
+            self.lines.pop_front();
+            self.dropped += 1;
+            self.cursor_row = self.cursor_row.saturating_sub(1);
+            self.screen_top = self.screen_top.saturating_sub(1);
+        }
+    }
+
+    /// 清空当前行，光标回到行首；与 TS 版本 `$LoggerKit.clearLine` 对应
+    pub fn clear_line(&mut self) {
+        self.erase_line(EraseMode::All);
+        self.cursor_col = 0;
+    }
+    /// 清空所有内容；与 TS 版本 `$LoggerKit.clearScreen` 对应
+    pub fn clear(&mut self) {
+        self.last_line = None;
+        self.dropped += self.lines.len();
+        self.lines.clear();
+        self.cursor_row = 0;
+        self.cursor_col = 0;
+        self.screen_top = 0;
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+    pub fn line(&self, index: usize) -> Option<&StyledString> {
+        self.lines.get(index)
+    }
+}
+
+fn to_cells(line: &StyledString) -> Vec<(char, Style)> {
+    line.spans()
+        .flat_map(|span| span.content.chars().map(move |c| (c, *span.attr)))
+        .collect()
+}
+
+fn from_cells(cells: &[(char, Style)]) -> StyledString {
+    let mut line = StyledString::new();
+    let mut text = String::new();
+    let mut text_style = None;
+    for &(c, style) in cells {
+        if text_style != Some(style) {
+            if let Some(text_style) = text_style {
+                line.append_styled(std::mem::take(&mut text), text_style);
+            }
+            text_style = Some(style);
+        }
+        text.push(c);
+    }
+    if let Some(text_style) = text_style {
+        line.append_styled(text, text_style);
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cursive::theme::Effect;
+
+    fn lines(buffer: &LogBuffer) -> Vec<&str> {
+        (0..buffer.len())
+            .map(|index| buffer.line(index).unwrap().source())
+            .collect()
+    }
+
+    #[test]
+    fn huge_cursor_moves_are_bounded() {
+        let mut buffer = LogBuffer::new();
+        buffer.write("a\x1b[4000000000Bb");
+        assert_eq!(buffer.len(), MAX_CSI_ARG + 1);
+        assert_eq!(buffer.line(MAX_CSI_ARG).unwrap().source(), " b");
+
+        let mut buffer = LogBuffer::new();
+        buffer.write("a\x1b[999999999Cb");
+        assert_eq!(
+            buffer.line(0).unwrap().source(),
+            format!("a{}b", " ".repeat(MAX_CSI_ARG))
+        );
+
+        let mut buffer = LogBuffer::new();
+        buffer.write("\x1b[99999999;1Hx");
+        assert_eq!(buffer.len(), MAX_CSI_ARG);
+        assert_eq!(buffer.line(MAX_CSI_ARG - 1).unwrap().source(), "x");
+
+        // 连续的光标指令不会累积
+        let mut buffer = LogBuffer::new();
+        buffer.write(&"\x1b[4000000000B".repeat(100));
+        buffer.write(&"\x1b[999999999C".repeat(100));
+        buffer.write("x");
+        assert_eq!(buffer.len(), MAX_CSI_ARG + 1);
+        assert_eq!(
+            buffer.line(MAX_CSI_ARG).unwrap().source().len(),
+            MAX_CSI_ARG + 1
+        );
+    }
+
+    #[test]
+    fn tab_padding_uses_the_style_of_the_text() {
+        let red = Style::from(BaseColor::Red.dark());
+        let green = Style::from(BaseColor::Green.dark());
+        let mut buffer = LogBuffer::new();
+        buffer.write_styled("a\tb\x1b[1m\tc\n", red);
+        buffer.write("\x1b[32mx\t\x1b[0my\n");
+        let cells = |index: usize| to_cells(buffer.line(index).unwrap());
+
+        let line = cells(0);
+        assert_eq!(
+            line.iter().map(|c| c.0).collect::<String>(),
+            "a       b       c"
+        );
+        assert!(line[..9].iter().all(|c| c.1 == red));
+        assert!(line[9..].iter().all(|c| c.1 == red.combine(Effect::Bold)));
+
+        let line = cells(1);
+        assert_eq!(line.iter().map(|c| c.0).collect::<String>(), "x       y");
+        assert!(line[..8].iter().all(|c| c.1 == green));
+        assert_eq!(line[8].1, Style::none());
+    }
+
+    #[test]
+    fn cursor_moves_rewrite_existing_lines() {
+        let mut buffer = LogBuffer::new();
+        buffer.write("one\ntwo\nthree\x1b[2A\x1b[1GONE\x1b[2;2HW");
+        assert_eq!(lines(&buffer), ["ONE", "tWo", "three"]);
+    }
+}
@@ -0,0 +1,278 @@
+use crate::log_buffer::LogBuffer;
+use core::cell::RefCell;
+
+use cursive::theme::Style;
+use cursive::utils::markup::StyledString;
+
+use std::rc::Rc;
+
+/// 页面默认的日志流，与 TS 版本 `Panel.loggerKit` 的 `{ name: "default", order: 100 }` 一致
+pub const DEFAULT_STREAM: &str = "default";
+pub const DEFAULT_STREAM_ORDER: i32 = 100;
+
+/// 日志流之间的分隔，只在日志流有内容时显示在它的前面
+#[derive(Clone)]
+pub enum LogSeparator {
+    /// 铺满宽度的横线
+    Rule,
+    Text(StyledString),
+}
+
+/// 合并显示中某一行的位置：记录行所在的日志流，以及它在日志流中的绝对行号（包含已丢弃的行）。
+/// 其它日志流写入、清除或者丢弃旧行时，锚点仍然指向同一行
+#[derive(Debug, Clone)]
+pub struct LogAnchor {
+    stream: String,
+    /// None 表示日志流前面的分隔
+    line: Option<usize>,
+}
+
+/// 合并显示时的一行
+pub enum LogLine<'a> {
+    Text(&'a StyledString),
+    Separator(&'a LogSeparator),
+}
+
+pub struct LogStream {
+    name: String,
+    order: i32,
+    buffer: LogBuffer,
+    separator: Option<LogSeparator>,
+}
+
+impl LogStream {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn buffer_mut(&mut self) -> &mut LogBuffer {
+        &mut self.buffer
+    }
+    fn separator_lines(&self) -> usize {
+        if self.separator.is_some() && !self.buffer.is_empty() {
+            1
+        } else {
+            0
+        }
+    }
+    fn rendered_len(&self) -> usize {
+        self.separator_lines() + self.buffer.len()
+    }
+}
+
+/// 一个页面里的多个日志流，各自独立写入与清除，按 order（相同时按名字）排序后合并显示；
+/// 对应 TS 版本 `Panel.createLoggerKit`
+pub struct LogStreams {
+    streams: Vec<LogStream>,
+}
+
+impl LogStreams {
+    pub fn new() -> Self {
+        LogStreams {
+            streams: Vec::new(),
+        }
+    }
+
+    /// 获取日志流，不存在时按指定的 order 创建
+    pub fn stream(&mut self, name: &str, order: i32) -> &mut LogStream {
+        let index = match self.streams.iter().position(|s| s.name == name) {
+            Some(index) => index,
+            None => {
+                let index = self
+                    .streams
+                    .iter()
+                    .position(|s| (s.order, s.name.as_str()) > (order, name))
+                    .unwrap_or(self.streams.len());
+                self.streams.insert(
+                    index,
+                    LogStream {
+                        name: name.to_owned(),
+                        order,
+                        buffer: LogBuffer::new(),
+                        separator: None,
+                    },
+                );
+                index
+            }
+        };
+        &mut self.streams[index]
+    }
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut LogStream> {
+        self.streams.iter_mut().find(|s| s.name == name)
+    }
+    pub fn set_separator(&mut self, name: &str, separator: Option<LogSeparator>) {
+        if let Some(stream) = self.get_mut(name) {
+            stream.separator = separator;
+        }
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &LogStream> {
+        self.streams.iter()
+    }
+
+    /// 合并后的总行数
+    pub fn len(&self) -> usize {
+        self.streams.iter().map(|s| s.rendered_len()).sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// 合并后第 index 行的锚点
+    pub fn anchor(&self, mut index: usize) -> Option<LogAnchor> {
+        for stream in &self.streams {
+            let separator_lines = stream.separator_lines();
+            if index < separator_lines {
+                return Some(LogAnchor {
+                    stream: stream.name.clone(),
+                    line: None,
+                });
+            }
+            index -= separator_lines;
+            if index < stream.buffer.len() {
+                return Some(LogAnchor {
+                    stream: stream.name.clone(),
+                    line: Some(stream.buffer.dropped() + index),
+                });
+            }
+            index -= stream.buffer.len();
+        }
+        None
+    }
+    /// 锚点当前在合并后的下标，它指向的行已经被丢弃或者清除时返回 None
+    pub fn position(&self, anchor: &LogAnchor) -> Option<usize> {
+        self.locate(anchor).ok()
+    }
+    /// 与 position 相同，但行已经不存在时返回它所在日志流中最接近的位置
+    pub fn nearest_position(&self, anchor: &LogAnchor) -> usize {
+        match self.locate(anchor) {
+            Ok(index) | Err(index) => index,
+        }
+    }
+    fn locate(&self, anchor: &LogAnchor) -> Result<usize, usize> {
+        let mut start = 0;
+        for stream in &self.streams {
+            if stream.name == anchor.stream {
+                let separator_lines = stream.separator_lines();
+                return match anchor.line {
+                    None if separator_lines > 0 => Ok(start),
+                    None => Err(start),
+                    Some(line) => match line.checked_sub(stream.buffer.dropped()) {
+                        Some(offset) if offset < stream.buffer.len() => {
+                            Ok(start + separator_lines + offset)
+                        }
+                        Some(_) => Err(start + stream.rendered_len()),
+                        None => Err(start + separator_lines),
+                    },
+                };
+            }
+            start += stream.rendered_len();
+        }
+        Err(0)
+    }
+    /// 合并后的第 index 行
+    pub fn line(&self, mut index: usize) -> Option<LogLine<'_>> {
+        for stream in &self.streams {
+            let separator_lines = stream.separator_lines();
+            if index < separator_lines {
+                return stream.separator.as_ref().map(LogLine::Separator);
+            }
+            index -= separator_lines;
+            if index < stream.buffer.len() {
+                return stream.buffer.line(index).map(LogLine::Text);
+            }
+            index -= stream.buffer.len();
+        }
+        None
+    }
+}
+
+/// 日志流的句柄，页面内容共享同一份 LogStreams，写入后会在下一帧显示
+#[derive(Clone)]
+pub struct PageLogger {
+    streams: Rc<RefCell<LogStreams>>,
+    name: String,
+}
+
+impl PageLogger {
+    pub fn new(streams: Rc<RefCell<LogStreams>>, name: &str, order: i32) -> Self {
+        streams.borrow_mut().stream(name, order);
+        PageLogger {
+            streams,
+            name: name.to_owned(),
+        }
+    }
+    fn with_buffer<F: FnOnce(&mut LogBuffer)>(&self, f: F) {
+        if let Some(stream) = self.streams.borrow_mut().get_mut(&self.name) {
+            f(stream.buffer_mut());
+        }
+    }
+    pub fn write(&self, text: &str) {
+        self.with_buffer(|buffer| buffer.write(text));
+    }
+    pub fn write_styled(&self, text: &str, base: Style) {
+        self.with_buffer(|buffer| buffer.write_styled(text, base));
+    }
+    pub fn clear_line(&self) {
+        self.with_buffer(|buffer| buffer.clear_line());
+    }
+    pub fn clear_screen(&self) {
+        self.with_buffer(|buffer| buffer.clear());
+    }
+    pub fn set_collapse_duplicates(&self, collapse: bool) {
+        self.with_buffer(|buffer| buffer.set_collapse_duplicates(collapse));
+    }
+    pub fn set_separator(&self, separator: Option<LogSeparator>) {
+        self.streams
+            .borrow_mut()
+            .set_separator(&self.name, separator);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn streams() -> (Rc<RefCell<LogStreams>>, PageLogger, PageLogger) {
+        let streams = Rc::new(RefCell::new(LogStreams::new()));
+        let tsc = PageLogger::new(streams.clone(), "tsc", 0);
+        let vite = PageLogger::new(streams.clone(), "vite", 200);
+        tsc.write("a\nb\nc\n");
+        vite.write("x\ny\n");
+        (streams, tsc, vite)
+    }
+
+    #[test]
+    fn anchor_is_stable_when_a_later_stream_is_cleared() {
+        let (streams, _tsc, vite) = streams();
+        let anchor = streams.borrow().anchor(1).unwrap();
+        vite.clear_screen();
+        assert_eq!(streams.borrow().position(&anchor), Some(1));
+    }
+
+    #[test]
+    fn anchor_follows_its_line_when_an_earlier_stream_is_cleared() {
+        let (streams, tsc, _vite) = streams();
+        let index = (0..streams.borrow().len())
+            .find(|index| match streams.borrow().line(*index) {
+                Some(LogLine::Text(line)) => line.source() == "y",
+                _ => false,
+            })
+            .unwrap();
+        let anchor = streams.borrow().anchor(index).unwrap();
+        tsc.clear_screen();
+        let streams = streams.borrow();
+        let position = streams.position(&anchor).unwrap();
+        match streams.line(position) {
+            Some(LogLine::Text(line)) => assert_eq!(line.source(), "y"),
+            _ => panic!("anchor moved off its line"),
+        }
+    }
+
+    #[test]
+    fn anchor_of_a_cleared_line_points_to_its_stream() {
+        let (streams, tsc, _vite) = streams();
+        let anchor = streams.borrow().anchor(1).unwrap();
+        tsc.clear_screen();
+        let streams = streams.borrow();
+        assert_eq!(streams.position(&anchor), None);
+        assert_eq!(streams.nearest_position(&anchor), 0);
+    }
+}
@@ -0,0 +1,213 @@
+use crate::log_streams::{LogAnchor, LogLine, LogSeparator, LogStreams};
+use core::cell::RefCell;
+
+use cursive::event::{Event, EventResult, Key, MouseButton, MouseEvent};
+use cursive::theme::{ColorStyle, Effect};
+use cursive::view::{CannotFocus, View};
+use cursive::{direction::Direction, Printer, Vec2};
+
+use std::cmp::min;
+use std::rc::Rc;
+
+/// 滚轮每次滚动的行数
+const WHEEL_STEP: usize = 3;
+
+/// 日志视图：只绘制可见的行；跟随模式下始终显示最新的输出，
+/// 向上滚动时暂停跟随，滚回底部（或按 End）后恢复。
+/// 上下方向键移动光标行，用来选中某一行（例如跳转到诊断信息所在的源码）
+pub struct LogView {
+    buffer: Rc<RefCell<LogStreams>>,
+    /// 暂停跟随时顶部的行，锚定在它所在的日志流中，这样丢弃旧行或者清除其它日志流时画面不会跳动
+    top: Option<LogAnchor>,
+    follow: bool,
+    /// 光标行，None 表示没有选中任何行
+    cursor: Option<LogAnchor>,
+    size: Vec2,
+}
+
+impl LogView {
+    pub fn new(buffer: Rc<RefCell<LogStreams>>) -> Self {
+        LogView {
+            buffer,
+            top: None,
+            follow: true,
+            cursor: None,
+            size: Vec2::zero(),
+        }
+    }
+    /// 光标行在缓冲区中的下标，所在的行已经被丢弃时返回 None
+    fn cursor_line(&self, buffer: &LogStreams) -> Option<usize> {
+        buffer.position(self.cursor.as_ref()?)
+    }
+    /// 光标行的文本内容
+    pub fn selected_text(&self) -> Option<String> {
+        let buffer = self.buffer.borrow();
+        match buffer.line(self.cursor_line(&buffer)?)? {
+            LogLine::Text(line) => Some(line.source().to_owned()),
+            LogLine::Separator(_) => None,
+        }
+    }
+    /// 移动光标行，没有光标时从可见区域的最后一行开始；必要时滚动使光标行可见
+    fn move_cursor(&mut self, delta: isize) {
+        let (line, top) = {
+            let buffer = self.buffer.borrow();
+            if buffer.is_empty() {
+                return;
+            }
+            let top = self.top_line(&buffer);
+            let line = match self.cursor_line(&buffer) {
+                Some(line) => line.saturating_add_signed(delta),
+                None => top + self.size.y.min(buffer.len() - top).saturating_sub(1),
+            };
+            let line = min(line, buffer.len() - 1);
+            self.cursor = buffer.anchor(line);
+            (line, top)
+        };
+        if line < top {
+            self.scroll_to(line);
+        } else if line >= top + self.size.y {
+            self.scroll_to(line + 1 - self.size.y);
+        }
+    }
+    fn click(&mut self, row: usize) {
+        if row >= self.size.y {
+            return;
+        }
+        let buffer = self.buffer.borrow();
+        let line = self.top_line(&buffer) + row;
+        if line < buffer.len() {
+            self.cursor = buffer.anchor(line);
+        }
+    }
+
+    fn max_top(&self, buffer: &LogStreams) -> usize {
+        buffer.len().saturating_sub(self.size.y)
+    }
+    /// 当前可见区域第一行在缓冲区中的下标
+    fn top_line(&self, buffer: &LogStreams) -> usize {
+        if self.follow {
+            self.max_top(buffer)
+        } else {
+            let top = self
+                .top
+                .as_ref()
+                .map_or(0, |top| buffer.nearest_position(top));
+            min(top, self.max_top(buffer))
+        }
+    }
+    fn scroll_to(&mut self, line: usize) {
+        let buffer = self.buffer.borrow();
+        let max_top = self.max_top(&buffer);
+        let line = min(line, max_top);
+        self.follow = line == max_top;
+        self.top = buffer.anchor(line);
+    }
+    fn scroll_up(&mut self, lines: usize) {
+        let top = self.top_line(&self.buffer.borrow());
+        self.scroll_to(top.saturating_sub(lines));
+    }
+    fn scroll_down(&mut self, lines: usize) {
+        let top = self.top_line(&self.buffer.borrow());
+        self.scroll_to(top + lines);
+    }
+    fn page_size(&self) -> usize {
+        self.size.y.saturating_sub(1).max(1)
+    }
+
+    fn draw_scrollbar(&self, printer: &Printer, buffer: &LogStreams, top: usize) {
+        let height = printer.size.y;
+        if buffer.len() <= height || height == 0 || printer.size.x == 0 {
+            return;
+        }
+        let x = printer.size.x - 1;
+        let thumb_height = (height * height / buffer.len()).max(1);
+        let thumb_top = (height - thumb_height) * top / self.max_top(buffer).max(1);
+        printer.with_color(ColorStyle::secondary(), |printer| {
+            printer.print_vline((x, 0), height, "│");
+        });
+        printer.with_color(ColorStyle::highlight(), |printer| {
+            printer.print_vline((x, thumb_top), thumb_height, " ");
+        });
+    }
+}
+
+impl View for LogView {
+    fn draw(&self, printer: &Printer) {
+        let buffer = self.buffer.borrow();
+        let top = self.top_line(&buffer);
+        let cursor = self.cursor_line(&buffer);
+        for y in 0..printer.size.y {
+            if cursor == Some(top + y) {
+                // 光标行用背景色标出，文本自身的颜色保持不变
+                printer.with_color(ColorStyle::highlight_inactive(), |printer| {
+                    printer.print_hline((0, y), printer.size.x, " ");
+                    if let Some(LogLine::Text(line)) = buffer.line(top + y) {
+                        printer.print_styled((0, y), line);
+                    }
+                });
+                continue;
+            }
+            match buffer.line(top + y) {
+                Some(LogLine::Text(line)) => printer.print_styled((0, y), line),
+                Some(LogLine::Separator(LogSeparator::Text(text))) => {
+                    printer.print_styled((0, y), text)
+                }
+                Some(LogLine::Separator(LogSeparator::Rule)) => {
+                    printer.with_color(ColorStyle::secondary(), |printer| {
+                        printer.print_hline((0, y), printer.size.x, "─");
+                    });
+                }
+                None => break,
+            }
+        }
+        self.draw_scrollbar(printer, &buffer, top);
+
+        if !self.follow {
+            let hidden = buffer.len().saturating_sub(top + printer.size.y);
+            let tip = format!(" ↓ {} more, End to follow ", hidden);
+            let x = printer.size.x.saturating_sub(tip.chars().count() + 1);
+            let y = printer.size.y.saturating_sub(1);
+            printer.with_effect(Effect::Reverse, |printer| printer.print((x, y), &tip));
+        }
+    }
+    fn layout(&mut self, size: Vec2) {
+        self.size = size;
+    }
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        constraint
+    }
+    fn take_focus(&mut self, _source: Direction) -> Result<EventResult, CannotFocus> {
+        Ok(EventResult::Consumed(None))
+    }
+    fn on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Key(Key::Up) => self.move_cursor(-1),
+            Event::Key(Key::Down) => self.move_cursor(1),
+            Event::Key(Key::PageUp) => self.scroll_up(self.page_size()),
+            Event::Key(Key::PageDown) => self.scroll_down(self.page_size()),
+            Event::Key(Key::Home) => self.scroll_to(0),
+            Event::Key(Key::End) => {
+                self.follow = true;
+                self.cursor = None;
+            }
+            Event::Mouse {
+                event: MouseEvent::WheelUp,
+                ..
+            } => self.scroll_up(WHEEL_STEP),
+            Event::Mouse {
+                event: MouseEvent::WheelDown,
+                ..
+            } => self.scroll_down(WHEEL_STEP),
+            Event::Mouse {
+                offset,
+                position,
+                event: MouseEvent::Press(MouseButton::Left),
+            } => match position.checked_sub(offset) {
+                Some(position) => self.click(position.y),
+                None => return EventResult::Ignored,
+            },
+            _ => return EventResult::Ignored,
+        }
+        EventResult::Consumed(None)
+    }
+}
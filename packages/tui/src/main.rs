@@ -1,19 +1,48 @@
+#[macro_use]
+extern crate lazy_static;
+
+mod ansi;
 mod browser;
+mod browser_content;
+mod code_frame;
+mod console;
+mod diagnostic;
+mod editor;
+mod log_buffer;
+mod log_streams;
+mod log_view;
+mod menu;
+mod output_parser;
 mod page;
+mod page_log;
 mod page_tab;
+mod palette;
+mod protocol;
+mod runner;
+mod server;
+mod spinner;
+mod status_bar;
 mod tabbar;
-mod browser_content;
+mod tsc_parser;
+mod utils;
+mod vite_parser;
+mod yarn_parser;
 
 use crate::browser::*;
+use crate::tabbar::TabWidthPolicy;
 use core::cell::RefCell;
-use cursive::event::Event;
+use cursive::event::{Event, Key};
 
-use cursive::theme::{BaseColor, Color, PaletteColor, Theme};
-use cursive::traits::*;
+use cursive::theme::{BaseColor, Color, PaletteColor};
+use cursive::view::Nameable;
+use cursive::views::Dialog;
 use cursive::views::DialogFocus;
-use cursive::views::{Dialog, LinearLayout, TextView};
-use cursive::{self, theme, views, With};
+use cursive::{self, views, With};
 
+use std::fs::File;
+use std::io::IsTerminal;
+use std::os::unix::io::FromRawFd;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 // #![feature(cell_leak)]
@@ -25,42 +54,65 @@ fn main() {
         .clone()
         .with(|theme| theme.shadow = false);
     siv.set_theme(theme);
+    spinner::init(siv.cb_sink().clone());
+    runner::init(siv.cb_sink().clone());
+    status_bar::init(siv.cb_sink().clone());
+    // `--editor "code -g {file}:{line}:{col}"` 指定在页面中按 Enter 打开源码位置的命令
+    editor::init(arg_value("--editor"));
 
     let mut browser = Browser::new("left".to_string());
+    if let Some(name) = arg_value("--tab-width") {
+        match TabWidthPolicy::parse(&name) {
+            Some(policy) => browser.set_tab_width_policy(policy),
+            None => {
+                eprintln!(
+                    "unknown --tab-width {:?}, expected even or proportional",
+                    name
+                );
+                std::process::exit(1);
+            }
+        }
+    }
     // browser.add_page("xxx", "xxxx\nyyyyy");
-    browser.add_page("tab1".to_owned());
-    browser.add_page("tab2".to_owned());
-
-    siv.add_fullscreen_layer(browser
-        // LinearLayout::horizontal()
-        //     // .with(|layout| {
-        //     //     browser.map(move |tab, content, index, selected| {
-        //     //         layout.add_child(TextView::new(tab));
-        //     //     });
-        //     // })
-        //     .child(TextView::new("tab2"))
-        //     .child(
-        //         browser
-        //             .fixed_width(10)
-        //             .fixed_height(10),
-        //     ),
-    );
-    // 调试
-    cursive::logger::init();
-    // Use some logging macros from the `log` crate.
-    log::error!("Something serious probably happened!");
-    log::warn!("Or did it?");
+
+    // 页面由外部驱动（例如 bfsp/bfsw）通过控制协议添加：
+    // 默认从 stdin 读取，`--fd <n>` 指定其它的文件描述符；stdin 是终端时它用于键盘输入，不读取指令。
+    // `--socket <path>` 在 Unix socket 上接受多个客户端，此时不读取 stdin
+    let socket = arg_value("--socket").map(PathBuf::from);
+    if let Some(path) = &socket {
+        if let Err(err) = server::spawn_server(path, siv.cb_sink().clone(), browser.id().to_owned())
+        {
+            eprintln!("failed to listen on {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+    }
+    match arg_value("--fd").and_then(|fd| fd.parse().ok()) {
+        Some(fd) => protocol::spawn_reader(
+            unsafe { File::from_raw_fd(fd) },
+            siv.cb_sink().clone(),
+            browser.id().to_owned(),
+        ),
+        None if socket.is_none() && !std::io::stdin().is_terminal() => protocol::spawn_reader(
+            std::io::stdin(),
+            siv.cb_sink().clone(),
+            browser.id().to_owned(),
+        ),
+        None => {}
+    }
+
+    let browser_id = browser.id().to_owned();
+    siv.add_fullscreen_layer(browser.with_name(browser_id.clone()));
+    // 调试：所有的日志进入全局控制台（Shift+F12），页面的日志同时显示在页面的调试面板（F12）
+    page_log::init();
     log::debug!("Logger initialized.");
-    log::info!("Starting!");
+    siv.add_global_callback(Event::Shift(Key::F12), console::toggle);
 
-    let mut i = 0;
-    siv.add_global_callback('l', move |_| {
-        log::trace!("{}", format!("Wooo-{}", i));
-        i += 1;
+    // Ctrl+P 打开命令面板
+    siv.add_global_callback(Event::CtrlChar('p'), move |s| {
+        palette::toggle(s, &browser_id)
     });
-
-    // 自定义调试日志面板
-    siv.add_global_callback('~', cursive::Cursive::toggle_debug_console);
+    // 在 Browser 上按 Esc 打开菜单栏，菜单在打开时根据当前的页面重新生成
+    menu::init(&mut siv);
 
     // 自定义ctrl-c
     siv.clear_global_callbacks(Event::CtrlChar('c'));
@@ -98,4 +150,36 @@ fn main() {
     });
 
     siv.run();
+    // 退出事件循环可能只是为了把终端交给编辑器，编辑器退出后恢复界面
+    while let Some(launch) = editor::take_pending() {
+        if let Err(err) = launch.run() {
+            siv.add_layer(Dialog::info(format!(
+                "Failed to run `{}`: {}",
+                launch.command_line(),
+                err
+            )));
+        }
+        siv.run();
+    }
+
+    if let Some(path) = socket {
+        std::fs::remove_file(path).ok();
+    }
+}
+
+/// 解析 `--name <value>` 或 `--name=<value>` 形式的参数
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == name {
+            return args.next();
+        }
+        if let Some(value) = arg
+            .strip_prefix(name)
+            .and_then(|rest| rest.strip_prefix('='))
+        {
+            return Some(value.to_owned());
+        }
+    }
+    None
 }
@@ -0,0 +1,420 @@
+use crate::browser::Browser;
+use crate::console;
+use crate::page_tab::TabStatus;
+use crate::palette;
+use crate::status_bar;
+
+use cursive::event::{Callback, EventResult, EventTrigger};
+use cursive::menu::{Item, Tree};
+use cursive::view::{Nameable, Resizable};
+use cursive::views::{Dialog, EditView};
+use cursive::Cursive;
+
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use unicode_width::UnicodeWidthStr;
+
+/// 菜单栏是否正在显示，只有显示期间才需要在它关闭后隐藏它
+static SHOWN: AtomicBool = AtomicBool::new(false);
+
+pub type MenuCallback = Rc<dyn Fn(&mut Cursive)>;
+
+/// 菜单栏中的菜单，按显示的顺序排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuSection {
+    File,
+    View,
+    Page,
+    Runner,
+    Help,
+}
+
+impl MenuSection {
+    pub const ALL: [MenuSection; 5] = [
+        MenuSection::File,
+        MenuSection::View,
+        MenuSection::Page,
+        MenuSection::Runner,
+        MenuSection::Help,
+    ];
+    pub fn title(&self) -> &'static str {
+        match self {
+            MenuSection::File => "File",
+            MenuSection::View => "View",
+            MenuSection::Page => "Page",
+            MenuSection::Runner => "Runner",
+            MenuSection::Help => "Help",
+        }
+    }
+}
+
+/// 运行时注册的菜单项：页面与 runner 注册在页面上，只在页面被选中时显示
+#[derive(Clone)]
+pub struct MenuEntry {
+    pub section: MenuSection,
+    pub label: String,
+    /// 显示在标签后面的快捷键，例如 `Ctrl+W`
+    pub shortcut: Option<String>,
+    pub callback: MenuCallback,
+}
+
+impl MenuEntry {
+    pub fn new<F>(section: MenuSection, label: &str, callback: F) -> Self
+    where
+        F: 'static + Fn(&mut Cursive),
+    {
+        MenuEntry {
+            section,
+            label: label.to_owned(),
+            shortcut: None,
+            callback: Rc::new(callback),
+        }
+    }
+    pub fn with_shortcut(mut self, shortcut: &str) -> Self {
+        self.shortcut = Some(shortcut.to_owned());
+        self
+    }
+    /// 菜单中显示的文字：标签补齐到 width 后接快捷键
+    fn menu_label(&self, width: usize) -> String {
+        match &self.shortcut {
+            Some(shortcut) => {
+                let padding = width.saturating_sub(self.label.width()) + 2;
+                format!("{}{}{}", self.label, " ".repeat(padding), shortcut)
+            }
+            None => self.label.clone(),
+        }
+    }
+}
+
+const KEYS_HELP: &str = "\
+Esc            menu
+Ctrl+P         command palette
+←/→ 1-9        switch page
+Alt+←/Alt+→    move the page
+F8/Shift+F8    next/previous page with errors
+↑/↓            move the cursor line
+Enter          open the location in $EDITOR
+End            follow the output
+i              inspect the page status
+Ctrl+W         close the page
+Ctrl+R         restart the runner
+Ctrl+T/Ctrl+K  stop/kill the runner
+F12            page debug panel
+Shift+F12      console";
+
+/// 对 Browser 执行返回 EventResult 的操作，并执行其中的回调（例如弹窗）
+fn with_browser<F>(s: &mut Cursive, browser_id: &str, f: F)
+where
+    F: FnOnce(&mut Browser) -> EventResult,
+{
+    if let Some(result) = s.call_on_name(browser_id, f) {
+        result.process(s);
+    }
+}
+
+/// 询问文件名后导出选中页面的内容
+fn export_selected_page(s: &mut Cursive, browser_id: &str) {
+    let uri = match s.call_on_name(browser_id, |browser: &mut Browser| browser.selected_uri()) {
+        Some(Some(uri)) => uri,
+        _ => return,
+    };
+    let default_path = format!("{}.log", uri.replace(['/', ':'], "_"));
+    let browser_id = browser_id.to_owned();
+    let export = move |s: &mut Cursive, path: &str| {
+        s.pop_layer();
+        let path = PathBuf::from(path);
+        let result = s.call_on_name(&browser_id, |browser: &mut Browser| {
+            browser.with_page(&uri, |page| page.export_output(&path))
+        });
+        match result.flatten() {
+            Some(Ok(count)) => status_bar::set_msg(
+                Some(format!("exported {} lines to {}", count, path.display())),
+                false,
+            ),
+            Some(Err(err)) => s.add_layer(Dialog::info(format!(
+                "Failed to write {}: {}",
+                path.display(),
+                err
+            ))),
+            None => {}
+        }
+    };
+    let export = Rc::new(export);
+    let on_submit = export.clone();
+    s.add_layer(
+        Dialog::around(
+            EditView::new()
+                .content(default_path)
+                .on_submit(move |s, path| on_submit(s, path))
+                .with_name("menu::export_path")
+                .min_width(40),
+        )
+        .title("Export page to")
+        .button("Export", move |s| {
+            let path = s
+                .call_on_name("menu::export_path", |edit: &mut EditView| {
+                    edit.get_content()
+                })
+                .unwrap_or_default();
+            export(s, &path);
+        })
+        .dismiss_button("Cancel"),
+    );
+}
+
+/// 内置的操作，菜单栏与命令面板共用
+pub fn builtin_entries(browser_id: &str) -> Vec<MenuEntry> {
+    let id = browser_id.to_owned();
+    let on_browser = |section, label, action: fn(&mut Browser) -> EventResult| {
+        let browser_id = id.clone();
+        MenuEntry::new(section, label, move |s| {
+            with_browser(s, &browser_id, action)
+        })
+    };
+    vec![
+        MenuEntry::new(MenuSection::File, "Quit", |s| s.quit()),
+        MenuEntry::new(MenuSection::View, "Console", console::toggle).with_shortcut("Shift+F12"),
+        {
+            let browser_id = id.clone();
+            MenuEntry::new(MenuSection::View, "Command palette", move |s| {
+                palette::toggle(s, &browser_id)
+            })
+            .with_shortcut("Ctrl+P")
+        },
+        on_browser(MenuSection::View, "Debug panel", |browser| {
+            browser.with_selected_page(|page| page.toggle_debug());
+            EventResult::Consumed(None)
+        })
+        .with_shortcut("F12"),
+        on_browser(MenuSection::View, "Next page", |browser| {
+            browser.select_next_page();
+            EventResult::Consumed(None)
+        })
+        .with_shortcut("→"),
+        on_browser(MenuSection::View, "Previous page", |browser| {
+            browser.select_prev_page();
+            EventResult::Consumed(None)
+        })
+        .with_shortcut("←"),
+        on_browser(MenuSection::View, "Next error", |browser| {
+            browser.select_page_with_status(TabStatus::Error, true);
+            EventResult::Consumed(None)
+        })
+        .with_shortcut("F8"),
+        on_browser(MenuSection::View, "Previous error", |browser| {
+            browser.select_page_with_status(TabStatus::Error, false);
+            EventResult::Consumed(None)
+        })
+        .with_shortcut("Shift+F8"),
+        on_browser(MenuSection::Page, "Move left", |browser| {
+            browser.move_selected_page(false);
+            EventResult::Consumed(None)
+        })
+        .with_shortcut("Alt+←"),
+        on_browser(MenuSection::Page, "Move right", |browser| {
+            browser.move_selected_page(true);
+            EventResult::Consumed(None)
+        })
+        .with_shortcut("Alt+→"),
+        on_browser(MenuSection::Page, "Inspect status", |browser| {
+            browser.inspect_selected_page()
+        })
+        .with_shortcut("i"),
+        on_browser(MenuSection::Page, "Open location", |browser| {
+            browser.open_selected_location()
+        })
+        .with_shortcut("Enter"),
+        on_browser(MenuSection::Page, "Clear output", |browser| {
+            browser.with_selected_page(|page| page.clear_output());
+            EventResult::Consumed(None)
+        }),
+        {
+            let browser_id = id.clone();
+            MenuEntry::new(MenuSection::Page, "Export output…", move |s| {
+                export_selected_page(s, &browser_id)
+            })
+        },
+        on_browser(MenuSection::Page, "Close", |browser| {
+            browser.request_close_selected_page()
+        })
+        .with_shortcut("Ctrl+W"),
+        MenuEntry::new(MenuSection::Help, "Keys", |s| {
+            s.add_layer(Dialog::info(KEYS_HELP).title("Keys"));
+        }),
+        MenuEntry::new(MenuSection::Help, "About", |s| {
+            s.add_layer(
+                Dialog::info(format!("pkgm tui {}", env!("CARGO_PKG_VERSION"))).title("About"),
+            );
+        }),
+    ]
+}
+
+/// 所有可以执行的操作：内置的操作，以及选中页面注册的菜单项
+pub fn actions(browser: &Browser) -> Vec<MenuEntry> {
+    let mut entries = builtin_entries(browser.id());
+    for section in MenuSection::ALL {
+        entries.extend(browser.menu_entries(section));
+    }
+    entries
+}
+
+/// Page 菜单开头的页面列表，● 标记选中的页面
+fn page_items(browser: &Browser) -> Tree {
+    let id = browser.id().to_owned();
+    let selected = browser.selected_uri();
+    let mut tree = Tree::new();
+    for (index, (uri, title)) in browser.page_titles().into_iter().enumerate() {
+        let mark = if selected.as_deref() == Some(uri.as_str()) {
+            '●'
+        } else {
+            ' '
+        };
+        let browser_id = id.clone();
+        tree.add_leaf(format!("{} {} {}", mark, index + 1, title), move |s| {
+            s.call_on_name(&browser_id, |browser: &mut Browser| {
+                browser.select_page(uri.clone())
+            });
+        });
+    }
+    tree
+}
+
+/// 执行菜单项之前先隐藏菜单栏
+fn hide_before_action(tree: &mut Tree) {
+    for item in tree.children.iter_mut() {
+        match item {
+            Item::Leaf { cb, .. } => {
+                let action = cb.clone();
+                *cb = Callback::from_fn(move |s| {
+                    hide(s);
+                    action(s);
+                });
+            }
+            Item::Subtree { tree, .. } => hide_before_action(Rc::make_mut(tree)),
+            Item::Delimiter => {}
+        }
+    }
+}
+
+/// 隐藏菜单栏：自动隐藏的菜单栏会被全屏的 Browser 盖住，所以打开时关闭自动隐藏，
+/// 让菜单栏单独占用第一行，执行菜单项或者按 Esc 关闭菜单后再恢复
+fn hide(s: &mut Cursive) {
+    SHOWN.store(false, Ordering::Relaxed);
+    s.set_autohide_menu(true);
+}
+
+/// 菜单栏与它的子菜单都已经关闭时隐藏它
+fn hide_if_closed(s: &mut Cursive) {
+    let menubar = s.menubar();
+    if !menubar.receive_events() && !menubar.has_submenu() {
+        hide(s);
+    }
+}
+
+/// 菜单栏按 Esc 关闭时不会通知我们，所以在菜单栏显示期间，
+/// 它不再接收事件后的第一个事件中隐藏它
+pub fn init(siv: &mut Cursive) {
+    siv.set_autohide_menu(true);
+    let cb_sink = siv.cb_sink().clone();
+    siv.set_on_pre_event_inner(
+        EventTrigger::from_fn(|_| SHOWN.load(Ordering::Relaxed)),
+        move |_| {
+            cb_sink.send(Box::new(hide_if_closed)).ok();
+            None
+        },
+    );
+}
+
+/// 根据当前的页面与注册的菜单项重新生成菜单栏，然后选中它；
+/// 由 Browser 处理 Esc 时调用，对话框与控制台在上层时 Esc 留给它们
+pub fn open(s: &mut Cursive, browser_id: &str) {
+    let menus = s.call_on_name(browser_id, |browser: &mut Browser| {
+        MenuSection::ALL
+            .iter()
+            .map(|section| {
+                let mut tree = match section {
+                    MenuSection::Page => page_items(browser),
+                    _ => Tree::new(),
+                };
+                let groups = [
+                    builtin_entries(browser.id())
+                        .into_iter()
+                        .filter(|entry| entry.section == *section)
+                        .collect::<Vec<_>>(),
+                    browser.menu_entries(*section),
+                ];
+                for entries in groups {
+                    if !tree.is_empty() && !entries.is_empty() {
+                        tree.add_delimiter();
+                    }
+                    let width = entries
+                        .iter()
+                        .map(|entry| entry.label.width())
+                        .max()
+                        .unwrap_or(0);
+                    for entry in entries {
+                        let callback = entry.callback.clone();
+                        tree.add_leaf(entry.menu_label(width), move |s| callback(s));
+                    }
+                }
+                if tree.is_empty() {
+                    tree.add_item(Item::leaf("(none)", |_| {}).disabled());
+                }
+                hide_before_action(&mut tree);
+                (section.title(), tree)
+            })
+            .collect::<Vec<_>>()
+    });
+    let menus = match menus {
+        Some(menus) => menus,
+        None => return,
+    };
+    let menubar = s.menubar();
+    menubar.clear();
+    for (title, tree) in menus {
+        menubar.add_subtree(title, tree);
+    }
+    SHOWN.store(true, Ordering::Relaxed);
+    s.set_autohide_menu(false);
+    s.select_menubar();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cursive::event::{Event, Key};
+
+    #[test]
+    fn esc_opens_the_menubar_only_from_the_browser() {
+        let mut siv = Cursive::new();
+        init(&mut siv);
+        siv.add_fullscreen_layer(Browser::new("browser".to_owned()).with_name("browser"));
+
+        // 对话框在上层时 Esc 留给对话框
+        siv.add_layer(Dialog::info("info"));
+        siv.on_event(Event::Key(Key::Esc));
+        assert!(!siv.menubar().receive_events());
+        siv.pop_layer();
+
+        siv.on_event(Event::Key(Key::Esc));
+        assert!(siv.menubar().receive_events());
+        assert!(!siv.menubar().autohide);
+        // 菜单栏还在使用时不会隐藏
+        hide_if_closed(&mut siv);
+        assert!(!siv.menubar().autohide);
+        // 在菜单栏中按 Esc 关闭后隐藏
+        siv.on_event(Event::Key(Key::Esc));
+        assert!(!siv.menubar().receive_events());
+        hide_if_closed(&mut siv);
+        assert!(siv.menubar().autohide);
+    }
+
+    #[test]
+    fn shortcuts_are_aligned_after_the_widest_label() {
+        let close = MenuEntry::new(MenuSection::Page, "Close", |_| {}).with_shortcut("Ctrl+W");
+        let clear = MenuEntry::new(MenuSection::Page, "Clear output", |_| {});
+        assert_eq!(close.menu_label(12), "Close         Ctrl+W");
+        assert_eq!(clear.menu_label(12), "Clear output");
+    }
+}
@@ -0,0 +1,125 @@
+use crate::ansi::{AnsiAction, AnsiParser};
+use crate::diagnostic::Diagnostic;
+use crate::page_tab::{PageTab, TabStatus};
+use crate::tsc_parser::TscParser;
+use crate::vite_parser::ViteParser;
+use crate::yarn_parser::YarnParser;
+
+use serde::Deserialize;
+
+/// 识别页面中某种工具的输出，据此自动更新 tab 的状态
+pub trait OutputParser {
+    /// 处理写入页面的一段输出，输出可能在任意位置被截断
+    fn feed(&mut self, text: &str, tab: &mut PageTab);
+    /// 是否把页面中连续相同的行合并显示
+    fn collapse_duplicates(&self) -> bool {
+        false
+    }
+    /// 移除解析器添加的状态并回到初始状态，例如 runner 重新启动时
+    fn reset(&mut self, tab: &mut PageTab);
+    /// 最近一轮输出中解析出来的诊断信息
+    fn diagnostics(&self) -> &[Diagnostic] {
+        &[]
+    }
+}
+
+/// 控制协议中用名字指定页面使用的解析器，例如 `"parser":"tsc"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParserKind {
+    Tsc,
+    Vite,
+    Yarn,
+}
+
+impl ParserKind {
+    pub fn create(self) -> Box<dyn OutputParser> {
+        match self {
+            ParserKind::Tsc => Box::new(TscParser::new()),
+            ParserKind::Vite => Box::new(ViteParser::new()),
+            ParserKind::Yarn => Box::new(YarnParser::new()),
+        }
+    }
+}
+
+/// 按行切分输出：去掉 ANSI 样式，`\r` 覆盖的进度行只保留最后的内容
+pub struct LineSplitter {
+    parser: AnsiParser,
+    line: String,
+    /// 遇到 `\r` 后光标回到行首，之后的文本会覆盖当前行
+    overwrite: bool,
+}
+
+impl LineSplitter {
+    pub fn new() -> Self {
+        LineSplitter {
+            parser: AnsiParser::new(),
+            line: String::new(),
+            overwrite: false,
+        }
+    }
+    /// 还没有结束的行，例如 `\r` 刷新的进度条
+    pub fn partial(&self) -> &str {
+        &self.line
+    }
+    /// 返回这段输出中所有完整的行，未结束的行留到下一次
+    pub fn feed(&mut self, text: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+        for action in self.parser.parse(text) {
+            match action {
+                AnsiAction::Text(text, _) => {
+                    if self.overwrite {
+                        self.line.clear();
+                        self.overwrite = false;
+                    }
+                    self.line.push_str(&text);
+                }
+                AnsiAction::Tab(_) => self.line.push('\t'),
+                AnsiAction::NewLine => {
+                    self.overwrite = false;
+                    lines.push(std::mem::take(&mut self.line));
+                }
+                AnsiAction::CarriageReturn => self.overwrite = true,
+                AnsiAction::EraseLine(_) => self.line.clear(),
+                _ => {}
+            }
+        }
+        lines
+    }
+}
+
+/// 解析器添加到 tab 上的状态，新一轮输出开始时可以只移除它们，不影响其它来源的状态
+pub struct ParserStatus {
+    reasons: Vec<(TabStatus, String)>,
+}
+
+impl ParserStatus {
+    pub fn new() -> Self {
+        ParserStatus {
+            reasons: Vec::new(),
+        }
+    }
+    pub fn add(&mut self, tab: &mut PageTab, status: TabStatus, reason: String) {
+        tab.add_status(status, reason.clone());
+        self.reasons.push((status, reason));
+    }
+    /// 移除某个状态的所有原因
+    pub fn remove(&mut self, tab: &mut PageTab, status: TabStatus) {
+        self.reasons.retain(|(reason_status, reason)| {
+            if *reason_status == status {
+                tab.del_status(status, reason.clone());
+                false
+            } else {
+                true
+            }
+        });
+    }
+    pub fn clear(&mut self, tab: &mut PageTab) {
+        for (status, reason) in self.reasons.drain(..) {
+            tab.del_status(status, reason);
+        }
+    }
+    pub fn has(&self, status: TabStatus) -> bool {
+        self.reasons.iter().any(|(s, _)| *s == status)
+    }
+}
@@ -1,48 +1,324 @@
-use crate::page_tab::PageTab;
-use core::cell::RefCell;
+use crate::code_frame::CodeFrame;
+use crate::diagnostic::{Diagnostic, Location};
+use crate::log_streams::{
+    LogLine, LogSeparator, LogStreams, PageLogger, DEFAULT_STREAM, DEFAULT_STREAM_ORDER,
+};
+use crate::log_view::LogView;
+use crate::menu::{MenuEntry, MenuSection};
+use crate::output_parser::OutputParser;
+use crate::page_log::{self, PageDebugPanel};
+use crate::page_tab::{PageTab, TabStatus};
+use crate::runner::Runner;
+use core::cell::{Cell, RefCell};
 
-use cursive::view::{SizeConstraint, View, ViewWrapper};
+use cursive::direction::Direction;
+use cursive::event::{Event, EventResult, Key};
+use cursive::theme::Style;
+use cursive::view::{CannotFocus, View};
 
-use cursive::views::{ResizedView, TextView};
+use cursive::views::ResizedView;
 use cursive::{self};
 use cursive::{Printer, Vec2};
 
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
+type CloseHook = Box<dyn FnOnce(&Page)>;
+/// 客户端 -> 它添加的 (状态, 原因)
+type ClientStatus = HashMap<String, HashSet<(TabStatus, String)>>;
+
 #[derive(Clone)]
 pub struct Page {
+    pub uri: String,
     // tab: Box<PageTab>,
     pub tab: Rc<RefCell<PageTab>>,
-    content: Rc<RefCell<ResizedView<TextView>>>,
+    streams: Rc<RefCell<LogStreams>>,
+    logger: PageLogger,
+    content: Rc<RefCell<ResizedView<LogView>>>,
+    busy: Rc<Cell<bool>>,
+    /// 创建这个页面的控制协议客户端，客户端断开时据此处理它的页面
+    owner: Rc<RefCell<Option<String>>>,
+    /// 各个控制协议客户端添加的状态，客户端断开时只移除它自己添加的
+    client_status: Rc<RefCell<ClientStatus>>,
+    /// runner 页面运行的子进程
+    runner: Rc<RefCell<Option<Runner>>>,
+    /// 识别页面输出的解析器，自动更新 tab 的状态
+    parser: Rc<RefCell<Option<Box<dyn OutputParser>>>>,
+    /// 光标行对应的源码位置的预览，显示在日志下方
+    preview: Rc<RefCell<Option<CodeFrame>>>,
+    /// 布局时分配给预览的高度，空间不够时为 0
+    preview_height: Rc<Cell<usize>>,
+    /// 页面自己的调试日志面板，F12 切换显示
+    debug: Rc<RefCell<PageDebugPanel>>,
+    debug_height: Rc<Cell<usize>>,
+    /// 页面与 runner 注册的菜单项，页面被选中时显示在菜单栏中
+    menu_entries: Rc<RefCell<Vec<MenuEntry>>>,
+    close_hooks: Rc<RefCell<Vec<CloseHook>>>,
 }
 impl Page {
-    pub fn new(title: String) -> Self {
-        let tab = Rc::new(RefCell::new(PageTab::new(title.clone())));
-        let content = Rc::new(RefCell::new(ResizedView::with_full_screen(TextView::new(
-            format!("status 404 on page {}", title),
+    pub fn new(uri: String) -> Self {
+        let tab = Rc::new(RefCell::new(PageTab::new(uri.clone())));
+        let streams = Rc::new(RefCell::new(LogStreams::new()));
+        let logger = PageLogger::new(streams.clone(), DEFAULT_STREAM, DEFAULT_STREAM_ORDER);
+        let content = Rc::new(RefCell::new(ResizedView::with_full_screen(LogView::new(
+            streams.clone(),
         ))));
+        let debug = Rc::new(RefCell::new(PageDebugPanel::new(uri.clone())));
         Page {
-            tab: tab,
+            uri,
+            tab,
+            streams,
+            logger,
             // tab: Box::new(tab),
             content,
+            busy: Rc::new(Cell::new(false)),
+            owner: Rc::new(RefCell::new(None)),
+            client_status: Rc::new(RefCell::new(HashMap::new())),
+            runner: Rc::new(RefCell::new(None)),
+            parser: Rc::new(RefCell::new(None)),
+            preview: Rc::new(RefCell::new(None)),
+            preview_height: Rc::new(Cell::new(0)),
+            debug,
+            debug_height: Rc::new(Cell::new(0)),
+            menu_entries: Rc::new(RefCell::new(Vec::new())),
+            close_hooks: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+    /// 获取页面中的日志流，不存在时按 order 创建；各个日志流按 order 排序后合并显示
+    pub fn logger(&self, name: &str, order: i32) -> PageLogger {
+        PageLogger::new(self.streams.clone(), name, order)
+    }
+    /// 追加输出到页面默认的日志流，支持 ANSI 样式与光标控制
+    pub fn write(&self, text: &str) {
+        self.logger.write(text);
+        self.parse_output(text);
+    }
+    pub fn write_styled(&self, text: &str, base: Style) {
+        self.logger.write_styled(text, base);
+        self.parse_output(text);
+    }
+    /// 写入指定的日志流，不存在时按 order 创建
+    pub fn write_stream(&self, name: &str, order: i32, text: &str) {
+        self.logger(name, order).write(text);
+        self.parse_output(text);
+    }
+    fn parse_output(&self, text: &str) {
+        if let Some(parser) = self.parser.borrow_mut().as_mut() {
+            parser.feed(text, &mut self.tab.borrow_mut());
+        }
+    }
+    /// 设置页面输出的解析器，替换掉的解析器添加的状态会被移除
+    pub fn set_parser(&mut self, parser: Option<Box<dyn OutputParser>>) {
+        let mut current = self.parser.borrow_mut();
+        if let Some(old) = current.as_mut() {
+            old.reset(&mut self.tab.borrow_mut());
+        }
+        self.logger.set_collapse_duplicates(
+            parser
+                .as_ref()
+                .is_some_and(|parser| parser.collapse_duplicates()),
+        );
+        *current = parser;
+    }
+    pub fn reset_parser(&self) {
+        if let Some(parser) = self.parser.borrow_mut().as_mut() {
+            parser.reset(&mut self.tab.borrow_mut());
+        }
+    }
+    /// 解析器从输出中识别出来的诊断信息
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.parser
+            .borrow()
+            .as_ref()
+            .map(|parser| parser.diagnostics().to_vec())
+            .unwrap_or_default()
+    }
+    /// 光标行对应的源码位置：优先使用行内的 `file:line:col`，
+    /// 否则查找消息出现在这一行中的诊断信息，例如 vite 错误的首行
+    pub fn selected_location(&self) -> Option<Location> {
+        let text = self.content.borrow().get_inner().selected_text()?;
+        if let Some(location) = Location::find(&text) {
+            return Some(location);
+        }
+        self.diagnostics()
+            .iter()
+            .find(|diagnostic| {
+                diagnostic.message.lines().any(|line| {
+                    let line = line.trim();
+                    !line.is_empty() && text.contains(line)
+                })
+            })
+            .map(|diagnostic| diagnostic.position())
+    }
+    /// runner 页面的工作目录，输出中的相对路径相对于它
+    pub fn cwd(&self) -> Option<PathBuf> {
+        self.with_runner(|runner, _| runner.spec().cwd.clone())
+            .flatten()
+    }
+    /// 光标行变化后更新预览，位置不变时不重新读取文件
+    fn update_preview(&self) {
+        let location = self.selected_location();
+        let mut preview = self.preview.borrow_mut();
+        if preview.as_ref().map(|frame| frame.location()) == location.as_ref() {
+            return;
+        }
+        *preview = location.map(|location| CodeFrame::load(location, self.cwd().as_deref()));
+    }
+    /// 清空页面所有日志流的内容
+    pub fn clear_output(&self) {
+        let mut streams = self.streams.borrow_mut();
+        let names: Vec<String> = streams.iter().map(|s| s.name().to_owned()).collect();
+        for name in names {
+            if let Some(stream) = streams.get_mut(&name) {
+                stream.buffer_mut().clear();
+            }
+        }
+    }
+    /// 把页面合并显示的内容以纯文本写入文件，返回写入的行数
+    pub fn export_output(&self, path: &Path) -> io::Result<usize> {
+        let mut file = File::create(path)?;
+        let streams = self.streams.borrow();
+        for index in 0..streams.len() {
+            match streams.line(index) {
+                Some(LogLine::Text(text)) => writeln!(file, "{}", text.source())?,
+                Some(LogLine::Separator(LogSeparator::Rule)) => {
+                    writeln!(file, "{}", "─".repeat(40))?
+                }
+                Some(LogLine::Separator(LogSeparator::Text(text))) => {
+                    writeln!(file, "{}", text.source())?
+                }
+                None => break,
+            }
+        }
+        Ok(streams.len())
+    }
+    /// 页面在忙碌时（手动标记，或者存在 Loading 状态），关闭前需要用户确认
+    pub fn is_busy(&self) -> bool {
+        self.busy.get() || self.tab.borrow().has_status(&TabStatus::Loading)
+    }
+    pub fn set_busy(&mut self, busy: bool) {
+        self.busy.set(busy);
+    }
+    pub fn owner(&self) -> Option<String> {
+        self.owner.borrow().clone()
+    }
+    pub fn set_owner(&mut self, owner: Option<String>) {
+        *self.owner.borrow_mut() = owner;
+    }
+    /// 客户端通过控制协议添加的状态
+    pub fn add_client_status(&mut self, client: &str, status: TabStatus, reason: String) {
+        self.client_status
+            .borrow_mut()
+            .entry(client.to_owned())
+            .or_default()
+            .insert((status, reason.clone()));
+        self.tab.borrow_mut().add_status(status, reason);
+    }
+    /// 状态被移除后，任何客户端都不再持有它
+    pub fn del_client_status(&mut self, status: TabStatus, reason: String) {
+        let key = (status, reason);
+        for reasons in self.client_status.borrow_mut().values_mut() {
+            reasons.remove(&key);
+        }
+        self.tab.borrow_mut().del_status(key.0, key.1);
+    }
+    /// 移除客户端添加的某个状态的所有原因，其它客户端也添加了的原因保留
+    pub fn clear_client_status(&mut self, client: &str, status: TabStatus) {
+        let mut client_status = self.client_status.borrow_mut();
+        let removed: Vec<String> = match client_status.get_mut(client) {
+            Some(reasons) => {
+                let removed = reasons
+                    .iter()
+                    .filter(|(reason_status, _)| *reason_status == status)
+                    .map(|(_, reason)| reason.clone())
+                    .collect();
+                reasons.retain(|(reason_status, _)| *reason_status != status);
+                removed
+            }
+            None => return,
+        };
+        let mut tab = self.tab.borrow_mut();
+        for reason in removed {
+            let key = (status, reason);
+            if !client_status.values().any(|reasons| reasons.contains(&key)) {
+                tab.del_status(key.0, key.1);
+            }
         }
     }
+    /// 客户端改用新的 id 后，它添加的状态也归属于新的 id
+    pub fn transfer_client_status(&mut self, from: &str, to: &str) {
+        let mut client_status = self.client_status.borrow_mut();
+        if let Some(reasons) = client_status.remove(from) {
+            client_status
+                .entry(to.to_owned())
+                .or_default()
+                .extend(reasons);
+        }
+    }
+    /// 把页面变成 runner 页面并启动进程，替换掉之前的 runner；页面关闭时结束进程
+    pub fn set_runner(&mut self, runner: Runner) {
+        self.with_runner(|old, page| old.clear_status(page));
+        let entries = runner.menu_entries();
+        let first = self.runner.borrow_mut().replace(runner).is_none();
+        if first {
+            for entry in entries {
+                self.add_menu_entry(entry);
+            }
+            self.on_close(|page| {
+                page.runner.borrow_mut().take();
+            });
+        }
+        self.with_runner(|runner, page| runner.start(page));
+    }
+    pub fn with_runner<F, R>(&self, f: F) -> Option<R>
+    where
+        F: FnOnce(&mut Runner, &Page) -> R,
+    {
+        let runner = self.runner.clone();
+        let mut runner = runner.borrow_mut();
+        runner.as_mut().map(|runner| f(runner, self))
+    }
+    /// 注册页面的菜单项，相同菜单中同名的菜单项会被替换
+    pub fn add_menu_entry(&mut self, entry: MenuEntry) {
+        let mut entries = self.menu_entries.borrow_mut();
+        entries.retain(|e| e.section != entry.section || e.label != entry.label);
+        entries.push(entry);
+    }
+    pub fn menu_entries(&self, section: MenuSection) -> Vec<MenuEntry> {
+        self.menu_entries
+            .borrow()
+            .iter()
+            .filter(|entry| entry.section == section)
+            .cloned()
+            .collect()
+    }
+    /// 注册页面关闭时的清理函数，在页面从 Browser 中移除前调用
+    pub fn on_close<F>(&mut self, f: F)
+    where
+        F: 'static + FnOnce(&Page),
+    {
+        self.close_hooks.borrow_mut().push(Box::new(f));
+    }
+    pub fn close(&mut self) {
+        let hooks: Vec<_> = self.close_hooks.borrow_mut().drain(..).collect();
+        for hook in hooks {
+            hook(self);
+        }
+        page_log::clear(&self.uri);
+    }
+    /// 显示或者隐藏调试面板
+    pub fn toggle_debug(&self) {
+        self.debug.borrow_mut().toggle();
+    }
     pub fn set_title(self: &mut Page, title: String) {
         self.tab.borrow_mut().set_content(title);
     }
     // pub fn get_tab(self: Page) -> Ref<'_, PageTab> {
     //     self.tab.borrow()
     // }
-    pub fn set_width(self: &mut Page, width: usize) {
-        self.content
-            .borrow_mut()
-            .set_width(SizeConstraint::Fixed(width));
-    }
-    pub fn set_height(self: &mut Page, height: usize) {
-        self.content
-            .borrow_mut()
-            .set_height(SizeConstraint::Fixed(height));
-    }
 }
 
 // impl ViewWrapper for Page {
@@ -50,13 +326,102 @@ impl Page {
 // }
 impl View for Page {
     fn draw(&self, printer: &Printer) {
-        self.content.borrow_mut().draw(printer);
+        let preview_height = self.preview_height.get();
+        let debug_height = self.debug_height.get();
+        let content_height = printer.size.y.saturating_sub(preview_height + debug_height);
+        self.content
+            .borrow_mut()
+            .draw(&printer.cropped((printer.size.x, content_height)));
+        if let Some(preview) = self
+            .preview
+            .borrow()
+            .as_ref()
+            .filter(|_| preview_height > 0)
+        {
+            preview.draw(
+                &printer
+                    .offset((0, content_height))
+                    .cropped((printer.size.x, preview_height)),
+            );
+        }
+        if debug_height > 0 {
+            self.debug.borrow().draw(
+                &printer
+                    .offset((0, content_height + preview_height))
+                    .cropped((printer.size.x, debug_height)),
+            );
+        }
     }
     fn required_size(&mut self, constraint: Vec2) -> Vec2 {
         self.content.borrow_mut().required_size(constraint)
     }
+    fn layout(&mut self, size: Vec2) {
+        // 调试面板占三分之一的高度
+        let debug_height = if self.debug.borrow().is_visible() {
+            size.y / 3
+        } else {
+            0
+        };
+        self.debug_height.set(debug_height);
+        // 预览至少给日志留下同样多的行，否则不显示
+        let available = size.y - debug_height;
+        let preview_height = self
+            .preview
+            .borrow()
+            .as_ref()
+            .map_or(0, |preview| preview.height());
+        let preview_height = if preview_height * 2 <= available {
+            preview_height
+        } else {
+            0
+        };
+        self.preview_height.set(preview_height);
+        // 伪终端的大小与日志区域一致，不包含预览与调试面板
+        let content_size = size.saturating_sub((0, preview_height + debug_height));
+        self.content.borrow_mut().layout(content_size);
+        self.with_runner(|runner, _| runner.resize(content_size));
+    }
+    fn take_focus(&mut self, source: Direction) -> Result<EventResult, CannotFocus> {
+        self.content.borrow_mut().take_focus(source)
+    }
+    fn on_event(&mut self, event: Event) -> EventResult {
+        if event == Event::Key(Key::F12) {
+            self.toggle_debug();
+            return EventResult::Consumed(None);
+        }
+        if self.debug.borrow().is_visible() {
+            if let EventResult::Consumed(callback) = self.debug.borrow_mut().on_event(event.clone())
+            {
+                return EventResult::Consumed(callback);
+            }
+        }
+        let result = self.content.borrow_mut().on_event(event);
+        self.update_preview();
+        result
+    }
 }
 
 // impl ViewWrapper for Page {
 //     wrap_impl!(self.content);
 // }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clearing_a_client_keeps_other_loading_reasons() {
+        let mut page = Page::new("test".to_owned());
+        page.tab
+            .borrow_mut()
+            .add_status(TabStatus::Loading, "compiling".to_owned());
+        page.add_client_status("a", TabStatus::Loading, "deploying".to_owned());
+        page.add_client_status("a", TabStatus::Loading, "shared".to_owned());
+        page.add_client_status("b", TabStatus::Loading, "shared".to_owned());
+        page.clear_client_status("a", TabStatus::Loading);
+        assert_eq!(
+            page.tab.borrow().status_reasons(),
+            vec![(TabStatus::Loading, vec!["compiling", "shared"])]
+        );
+    }
+}
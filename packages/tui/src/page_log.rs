@@ -0,0 +1,183 @@
+use crate::console;
+use crate::utils::{format_time, truncate_str};
+
+use cursive::event::{Event, EventResult};
+use cursive::theme::{BaseColor, ColorStyle, Effect, Style};
+use cursive::view::View;
+use cursive::{Printer, Vec2};
+
+use log::{Level, LevelFilter};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// 以 `page::<uri>` 作为 target 的日志记录只显示在对应页面的调试面板中，例如
+/// `log::debug!(target: &page_log::target(&uri), "...")`
+pub const TARGET_PREFIX: &str = "page::";
+/// 每个页面保留的日志条数
+const PAGE_LOG_LIMIT: usize = 1_000;
+
+pub fn target(uri: &str) -> String {
+    format!("{}{}", TARGET_PREFIX, uri)
+}
+
+pub struct Record {
+    pub level: Level,
+    pub time: SystemTime,
+    pub message: String,
+}
+
+lazy_static! {
+    static ref PAGE_LOGS: Mutex<HashMap<String, VecDeque<Record>>> = Mutex::new(HashMap::new());
+}
+
+/// 按 target 分发日志：所有日志都进入全局控制台，页面的日志同时进入页面自己的队列
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        // cursive 每次布局都会输出调试日志，只保留它的警告与错误
+        let target = metadata.target();
+        metadata.level() <= Level::Warn
+            || !(target.starts_with("cursive_core") || target.starts_with("cursive::"))
+    }
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        console::push(record);
+        let uri = match record.target().strip_prefix(TARGET_PREFIX) {
+            Some(uri) => uri,
+            None => return,
+        };
+        let mut logs = PAGE_LOGS.lock().unwrap();
+        let logs = logs.entry(uri.to_owned()).or_default();
+        if logs.len() >= PAGE_LOG_LIMIT {
+            logs.pop_front();
+        }
+        logs.push_back(Record {
+            level: record.level(),
+            time: SystemTime::now(),
+            message: record.args().to_string(),
+        });
+    }
+    fn flush(&self) {}
+}
+
+/// 安装应用的 logger，只能调用一次
+pub fn init() {
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(LevelFilter::Trace);
+}
+
+/// 清空页面的日志
+pub fn clear(uri: &str) {
+    PAGE_LOGS.lock().unwrap().remove(uri);
+}
+
+pub fn level_style(level: Level) -> Style {
+    match level {
+        Level::Error => BaseColor::Red.light().into(),
+        Level::Warn => BaseColor::Yellow.light().into(),
+        Level::Info => BaseColor::Green.light().into(),
+        Level::Debug => BaseColor::Cyan.light().into(),
+        Level::Trace => Effect::Dim.into(),
+    }
+}
+
+/// 把过滤级别调高或调低一级，到达 Off 或 Trace 后保持不变
+pub fn step_level(level: LevelFilter, more: bool) -> LevelFilter {
+    const LEVELS: [LevelFilter; 6] = [
+        LevelFilter::Off,
+        LevelFilter::Error,
+        LevelFilter::Warn,
+        LevelFilter::Info,
+        LevelFilter::Debug,
+        LevelFilter::Trace,
+    ];
+    let index = LEVELS.iter().position(|l| *l == level).unwrap_or(0);
+    if more {
+        LEVELS[(index + 1).min(LEVELS.len() - 1)]
+    } else {
+        LEVELS[index.saturating_sub(1)]
+    }
+}
+
+/// 页面的调试面板：显示在页面内容下方，最新的日志在最下面；
+/// `+`/`-` 调整显示的级别，`c` 清空
+pub struct PageDebugPanel {
+    uri: String,
+    visible: bool,
+    level: LevelFilter,
+}
+
+impl PageDebugPanel {
+    pub fn new(uri: String) -> Self {
+        PageDebugPanel {
+            uri,
+            visible: false,
+            level: LevelFilter::Debug,
+        }
+    }
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+    pub fn clear(&self) {
+        clear(&self.uri);
+    }
+}
+
+impl View for PageDebugPanel {
+    fn draw(&self, printer: &Printer) {
+        let title = format!(
+            "─ debug {} [{}] ─ +/- level, c clear, F12 close ",
+            self.uri, self.level
+        );
+        printer.with_color(ColorStyle::secondary(), |printer| {
+            printer.print_hline((0, 0), printer.size.x, "─");
+            printer.print((0, 0), &truncate_str(&title, printer.size.x));
+        });
+        let height = printer.size.y.saturating_sub(1);
+        let logs = PAGE_LOGS.lock().unwrap();
+        let records: Vec<&Record> = logs
+            .get(&self.uri)
+            .into_iter()
+            .flatten()
+            .filter(|record| record.level <= self.level)
+            .collect();
+        let skip = records.len().saturating_sub(height);
+        for (y, record) in records[skip..].iter().enumerate() {
+            let y = y + 1;
+            let time = format_time(record.time);
+            printer.with_effect(Effect::Dim, |printer| printer.print((0, y), &time));
+            let x = time.len() + 1;
+            let level = format!("{:<5}", record.level);
+            printer.with_style(level_style(record.level), |printer| {
+                printer.print((x, y), &level)
+            });
+            let x = x + level.len() + 1;
+            let message = record.message.lines().next().unwrap_or_default();
+            printer.print(
+                (x, y),
+                &truncate_str(message, printer.size.x.saturating_sub(x)),
+            );
+        }
+    }
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        constraint
+    }
+    fn on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Char('+') => self.level = step_level(self.level, true),
+            Event::Char('-') => self.level = step_level(self.level, false),
+            Event::Char('c') => self.clear(),
+            _ => return EventResult::Ignored,
+        }
+        EventResult::Consumed(None)
+    }
+}
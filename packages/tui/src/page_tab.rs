@@ -1,18 +1,47 @@
+use crate::spinner::{self, SpinnerGuard};
+use crate::utils::truncate_str;
+use cursive::theme::{BaseColor, Color, Effect, Style};
+use cursive::utils::markup::StyledString;
 use cursive::view::View;
-use cursive::views::TextView;
 use cursive::{Printer, Vec2};
-use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
-use std::rc::Rc;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use unicode_width::UnicodeWidthStr;
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+/// 变体按严重程度排列，越靠前越严重，徽标也按这个顺序显示；
+/// 在控制协议中使用小写的名字，例如 `"loading"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TabStatus {
-    Success,
     Error,
     Warn,
     Loading,
+    Success,
     Info,
 }
+impl TabStatus {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            TabStatus::Error => "X",
+            TabStatus::Warn => "⚠",
+            TabStatus::Loading => spinner::frame(),
+            TabStatus::Success => "✓",
+            TabStatus::Info => "i",
+        }
+    }
+    pub fn color(&self) -> Color {
+        match self {
+            TabStatus::Error => BaseColor::Red.light(),
+            TabStatus::Warn => BaseColor::Yellow.light(),
+            TabStatus::Loading => BaseColor::Cyan.light(),
+            TabStatus::Success => BaseColor::Green.light(),
+            TabStatus::Info => BaseColor::Blue.light(),
+        }
+    }
+    pub fn style(&self) -> Style {
+        Style::from(self.color()).combine(Effect::Bold)
+    }
+}
 // impl IndexMut<TabStatus> for HashMap<TabStatus,dyn Any> {
 //     fn index_mut<'a>(&'a mut self, index: TabStatus) -> &'a mut Weight {
 //         println!("Accessing {:?}-side of balance mutably", index);
@@ -24,85 +53,185 @@ pub enum TabStatus {
 // }
 
 pub struct PageTab {
-    text: TextView,
-    icon: Rc<RefCell<TextView>>,
-    id: String,
-    status: HashMap<TabStatus, HashSet<String>>,
+    title: String,
+    status: BTreeMap<TabStatus, HashSet<String>>,
+    /// 存在 Loading 状态时持有，保持 spinner 动画运转
+    spinner: Option<SpinnerGuard>,
+    /// 任务的进度，0.0 到 1.0，显示在状态栏上
+    progress: Option<f32>,
 }
 
 impl PageTab {
     pub fn new(id: String) -> Self {
         PageTab {
-            id: id.clone(),
-            text: TextView::new(id),
-            icon: Rc::new(RefCell::new(TextView::new(""))),
-            status: HashMap::new(),
+            title: id,
+            status: BTreeMap::new(),
+            spinner: None,
+            progress: None,
             // view: FocusTracker::new(&textview),
         }
     }
 
     pub fn set_content(&mut self, title: String) {
-        self.text.set_content(title);
+        self.title = title;
+    }
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+    pub fn set_progress(&mut self, progress: Option<f32>) {
+        self.progress = progress.map(|progress| progress.clamp(0.0, 1.0));
+    }
+    pub fn progress(&self) -> Option<f32> {
+        self.progress
+    }
+    fn icon_width(&self) -> usize {
+        self.badge().width()
+    }
+    /// 不截断时完整显示 tab 所需的宽度
+    pub fn natural_width(&self) -> usize {
+        let icon_width = self.icon_width();
+        let gap = if icon_width > 0 { 1 } else { 0 };
+        icon_width + gap + self.title.width()
     }
 
     pub fn add_status(&mut self, status: TabStatus, reason: String) {
-        let mut changed = false;
-        match self.status.get_mut(&status) {
-            Some(reasons) => {
-                changed = reasons.insert(reason);
-            }
+        let changed = match self.status.get_mut(&status) {
+            Some(reasons) => reasons.insert(reason),
             None => {
                 let mut hash_set = HashSet::<String>::new();
-                changed = hash_set.insert(reason);
+                let changed = hash_set.insert(reason);
                 self.status.insert(status, hash_set);
+                changed
             }
-        }
+        };
         if changed {
-            self.update_icon()
+            self.update_spinner()
         }
     }
     pub fn del_status(&mut self, status: TabStatus, reason: String) {
         let mut changed = false;
-        match self.status.get_mut(&status) {
-            Some(reasons) => {
-                changed = reasons.remove(&reason);
-            }
-            None => {}
+        if let Some(reasons) = self.status.get_mut(&status) {
+            changed = reasons.remove(&reason);
         }
         if changed {
-            self.update_icon()
+            self.update_spinner()
+        }
+    }
+    /// 所有生效中的状态及其原因，按严重程度排序，原因按字母排序
+    pub fn status_reasons(&self) -> Vec<(TabStatus, Vec<&str>)> {
+        self.status
+            .iter()
+            .filter(|(_, reasons)| !reasons.is_empty())
+            .map(|(status, reasons)| {
+                let mut reasons: Vec<&str> = reasons.iter().map(|r| r.as_str()).collect();
+                reasons.sort_unstable();
+                (*status, reasons)
+            })
+            .collect()
+    }
+    /// 最严重的状态的第一个原因，用于状态栏的摘要
+    pub fn top_reason(&self) -> Option<(TabStatus, &str)> {
+        self.status_reasons()
+            .into_iter()
+            .next()
+            .map(|(status, reasons)| (status, reasons[0]))
+    }
+    pub fn has_status(&self, status: &TabStatus) -> bool {
+        self.status
+            .get(status)
+            .is_some_and(|reasons| !reasons.is_empty())
+    }
+    /// 只要还有 Loading 的原因，spinner 就保持转动；全部移除后停止，空闲时不再重绘
+    fn update_spinner(&mut self) {
+        let loading = self.has_status(&TabStatus::Loading);
+        if loading && self.spinner.is_none() {
+            self.spinner = Some(SpinnerGuard::new());
+        } else if !loading {
+            self.spinner = None;
         }
     }
-    fn update_icon(&mut self) {
-        let mut icon: String = "".to_owned();
-        for status in self.status.keys() {
-            match status {
-                TabStatus::Success => icon += "✓",
-                TabStatus::Error => icon += "X",
-                TabStatus::Warn => icon += "⚠",
-                TabStatus::Loading => icon += "~",
-                TabStatus::Info => icon += "i",
+    /// 状态徽标，最严重的状态在前，多个原因时附带数量，例如 "X3 ⚠2"
+    pub fn badge(&self) -> StyledString {
+        let mut badge = StyledString::new();
+        for (status, reasons) in self.status.iter() {
+            if reasons.is_empty() {
+                continue;
+            }
+            if !badge.is_empty() {
+                badge.append_plain(" ");
+            }
+            let mut text = status.symbol().to_owned();
+            if reasons.len() > 1 {
+                text += &reasons.len().to_string();
             }
+            badge.append_styled(text, status.style());
         }
-        self.icon.borrow_mut().set_content(icon);
+        badge
     }
 }
 
 impl View for PageTab {
+    /// 标题放不下时按显示宽度截断，以 "…" 结尾
     fn draw(&self, printer: &Printer) {
-        self.icon.borrow().draw(printer);
-        let icon_draw_size = self.icon.borrow_mut().required_size(printer.output_size);
-        self.text
-            .draw(&printer.offset(icon_draw_size.map_x(|x| x + 1)));
+        let badge = self.badge();
+        let mut icon_width = badge.width();
+        if icon_width > 0 {
+            printer.print_styled((0, 0), &badge);
+            icon_width += 1;
+        }
+        let title_width = printer.size.x.saturating_sub(icon_width);
+        printer.print((icon_width, 0), &truncate_str(&self.title, title_width));
+    }
+    fn required_size(&mut self, _constraint: Vec2) -> Vec2 {
+        Vec2::new(self.natural_width(), 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(badge: &StyledString) -> Vec<(&str, Style)> {
+        badge
+            .spans()
+            .map(|span| (span.content, *span.attr))
+            .filter(|(content, _)| *content != " ")
+            .collect()
     }
-    fn layout(&mut self, size: Vec2) {
-        self.icon.borrow_mut().layout(size);
-        self.text.layout(size);
+
+    #[test]
+    fn badge_orders_by_severity_and_counts_reasons() {
+        let mut tab = PageTab::new("tsc".to_owned());
+        tab.add_status(TabStatus::Success, "no errors".to_owned());
+        tab.add_status(TabStatus::Warn, "src/b.ts".to_owned());
+        tab.add_status(TabStatus::Warn, "src/c.ts".to_owned());
+        for file in ["src/a.ts", "src/d.ts", "src/e.ts"] {
+            tab.add_status(TabStatus::Error, file.to_owned());
+        }
+        // 同一个原因只计算一次
+        tab.add_status(TabStatus::Error, "src/a.ts".to_owned());
+        let badge = tab.badge();
+        assert_eq!(badge.source(), "X3 ⚠2 ✓");
+        assert_eq!(
+            spans(&badge),
+            [
+                ("X3", TabStatus::Error.style()),
+                ("⚠2", TabStatus::Warn.style()),
+                ("✓", TabStatus::Success.style()),
+            ]
+        );
+        assert_eq!(tab.natural_width(), "X3 ⚠2 ✓ tsc".width());
     }
 
-    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
-        let text_require_size = self.text.required_size(constraint);
-        let icon_require_size = self.icon.borrow_mut().required_size(constraint);
-        icon_require_size + Vec2::new(1, 0) + text_require_size
+    #[test]
+    fn badge_skips_statuses_without_reasons() {
+        let mut tab = PageTab::new("dev".to_owned());
+        tab.add_status(TabStatus::Error, "build".to_owned());
+        tab.add_status(TabStatus::Info, "running".to_owned());
+        tab.del_status(TabStatus::Error, "build".to_owned());
+        assert_eq!(tab.badge().source(), "i");
+        tab.del_status(TabStatus::Info, "running".to_owned());
+        assert!(tab.badge().is_empty());
+        assert_eq!(tab.natural_width(), 3);
     }
 }
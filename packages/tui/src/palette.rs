@@ -0,0 +1,440 @@
+use crate::browser::Browser;
+use crate::menu::{self, MenuCallback};
+use crate::output_parser::ParserKind;
+use crate::runner::{Runner, RunnerSpec};
+
+use cursive::event::Key;
+use cursive::theme::{BaseColor, Effect, Style};
+use cursive::utils::markup::StyledString;
+use cursive::view::{Nameable, Resizable};
+use cursive::views::{Dialog, EditView, LinearLayout, OnEventView, SelectView};
+use cursive::{Cursive, With};
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Mutex;
+use unicode_width::UnicodeWidthStr;
+
+/// 命令面板图层与其中视图的名字
+const PALETTE_LAYER: &str = "palette";
+const PALETTE_INPUT: &str = "palette::input";
+const PALETTE_LIST: &str = "palette::list";
+/// 最多显示的结果数
+const MAX_RESULTS: usize = 12;
+/// 最近一次使用的条目额外的得分，之后每使用一个其它条目减半
+const RECENT_BONUS: i64 = 32;
+
+/// 条目最近一次被使用的序号，用于把常用的条目排在前面
+struct RecentUse {
+    uses: HashMap<String, u64>,
+    next: u64,
+}
+
+impl RecentUse {
+    fn touch(&mut self, key: String) {
+        self.uses.insert(key, self.next);
+        self.next += 1;
+    }
+    fn bonus(&self, key: &str) -> i64 {
+        match self.uses.get(key) {
+            Some(seq) => RECENT_BONUS >> (self.next - 1 - seq).min(62),
+            None => 0,
+        }
+    }
+}
+
+lazy_static! {
+    static ref RECENT: Mutex<RecentUse> = Mutex::new(RecentUse {
+        uses: HashMap::new(),
+        next: 0,
+    });
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PaletteKind {
+    Page,
+    Action,
+    Script,
+}
+
+impl PaletteKind {
+    fn title(&self) -> &'static str {
+        match self {
+            PaletteKind::Page => "page",
+            PaletteKind::Action => "action",
+            PaletteKind::Script => "script",
+        }
+    }
+}
+
+struct PaletteItem {
+    kind: PaletteKind,
+    /// 参与匹配的文本
+    label: String,
+    /// 显示在后面的补充信息：页面的 uri、快捷键、脚本的命令
+    detail: String,
+    callback: MenuCallback,
+}
+
+impl PaletteItem {
+    fn key(&self) -> String {
+        format!("{}:{}", self.kind.title(), self.label)
+    }
+}
+
+/// 模糊匹配：pattern 中的字符（忽略大小写与空白）依次出现在 text 中即匹配；
+/// 连续的、位于单词开头的字符得分更高，跳过的字符扣分。返回得分与匹配到的字符下标
+fn fuzzy_match(pattern: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let lower = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let chars: Vec<char> = text.chars().collect();
+    let is_word_start = |index: usize| {
+        index == 0
+            || !chars[index - 1].is_alphanumeric()
+            || (chars[index - 1].is_lowercase() && chars[index].is_uppercase())
+    };
+    let mut positions: Vec<usize> = Vec::new();
+    let mut score = 0;
+    let mut from = 0;
+    for c in pattern.chars().filter(|c| !c.is_whitespace()).map(lower) {
+        let index = (from..chars.len()).find(|&index| lower(chars[index]) == c)?;
+        score += 1;
+        if positions.last().is_some_and(|last| last + 1 == index) {
+            score += 5;
+        } else if is_word_start(index) {
+            score += 8;
+        }
+        if index == 0 {
+            score += 4;
+        }
+        score -= (index - from).min(3) as i64;
+        positions.push(index);
+        from = index + 1;
+    }
+    Some((score, positions))
+}
+
+/// 按匹配得分与最近使用的顺序排序，返回条目的下标与匹配到的字符
+fn rank(items: &[PaletteItem], query: &str) -> Vec<(usize, Vec<usize>)> {
+    let recent = RECENT.lock().unwrap();
+    let mut results: Vec<(i64, usize, Vec<usize>)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            let (score, positions) = fuzzy_match(query, &item.label)?;
+            Some((score + recent.bonus(&item.key()), index, positions))
+        })
+        .collect();
+    results.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    results
+        .into_iter()
+        .take(MAX_RESULTS)
+        .map(|(_, index, positions)| (index, positions))
+        .collect()
+}
+
+/// 一行结果：类别、标签（高亮匹配到的字符，补齐到 width）与补充信息
+fn styled_row(item: &PaletteItem, positions: &[usize], width: usize) -> StyledString {
+    let mut row = StyledString::new();
+    row.append_styled(format!("{:<7}", item.kind.title()), Effect::Dim);
+    let matched = Style::from(BaseColor::Yellow.light()).combine(Effect::Bold);
+    for (index, c) in item.label.chars().enumerate() {
+        if positions.contains(&index) {
+            row.append_styled(c.to_string(), matched);
+        } else {
+            row.append_plain(c.to_string());
+        }
+    }
+    if !item.detail.is_empty() {
+        let padding = width.saturating_sub(item.label.width()) + 2;
+        row.append_plain(" ".repeat(padding));
+        row.append_styled(&item.detail, Effect::Dim);
+    }
+    row
+}
+
+/// 页面、菜单中的操作（包括页面与 runner 注册的操作），以及 package.json 中的脚本
+fn collect_items(browser: &Browser) -> Vec<PaletteItem> {
+    let mut items = Vec::new();
+    for (uri, title) in browser.page_titles() {
+        let browser_id = browser.id().to_owned();
+        let detail = if title == uri {
+            String::new()
+        } else {
+            uri.clone()
+        };
+        items.push(PaletteItem {
+            kind: PaletteKind::Page,
+            label: title,
+            detail,
+            callback: Rc::new(move |s| {
+                s.call_on_name(&browser_id, |browser: &mut Browser| {
+                    browser.select_page(uri.clone())
+                });
+            }),
+        });
+    }
+    for entry in menu::actions(browser) {
+        if entry.label == "Command palette" {
+            continue;
+        }
+        items.push(PaletteItem {
+            kind: PaletteKind::Action,
+            label: format!("{}: {}", entry.section.title(), entry.label),
+            detail: entry.shortcut.unwrap_or_default(),
+            callback: entry.callback,
+        });
+    }
+    if let Ok(cwd) = std::env::current_dir() {
+        for (name, command) in package_scripts(&cwd) {
+            let spec = script_spec(&cwd, &name);
+            let browser_id = browser.id().to_owned();
+            let parser = guess_parser(&command);
+            items.push(PaletteItem {
+                kind: PaletteKind::Script,
+                label: name.clone(),
+                detail: command,
+                callback: Rc::new(move |s| {
+                    s.call_on_name(&browser_id, |browser: &mut Browser| {
+                        run_script(browser, &name, spec.clone(), parser)
+                    });
+                }),
+            });
+        }
+    }
+    items
+}
+
+/// 目录下 package.json 中的 `scripts`，按名字排序
+fn package_scripts(dir: &Path) -> Vec<(String, String)> {
+    let package: serde_json::Value = match std::fs::read(dir.join("package.json"))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+    {
+        Some(package) => package,
+        None => return Vec::new(),
+    };
+    let mut scripts: Vec<(String, String)> = package
+        .get("scripts")
+        .and_then(|scripts| scripts.as_object())
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, command)| Some((name.clone(), command.as_str()?.to_owned())))
+        .collect();
+    scripts.sort();
+    scripts
+}
+
+/// 按目录中的 lock 文件选择包管理器来运行脚本
+fn script_spec(dir: &Path, name: &str) -> RunnerSpec {
+    let (program, args) = if dir.join("yarn.lock").exists() {
+        ("yarn", vec![name.to_owned()])
+    } else if dir.join("pnpm-lock.yaml").exists() {
+        ("pnpm", vec!["run".to_owned(), name.to_owned()])
+    } else {
+        ("npm", vec!["run".to_owned(), name.to_owned()])
+    };
+    RunnerSpec {
+        program: program.to_owned(),
+        args,
+        cwd: Some(dir.to_path_buf()),
+        env: HashMap::new(),
+        pty: true,
+    }
+}
+
+fn guess_parser(command: &str) -> Option<ParserKind> {
+    let words: Vec<&str> = command.split_whitespace().collect();
+    if words.contains(&"tsc") {
+        Some(ParserKind::Tsc)
+    } else if words.contains(&"vite") {
+        Some(ParserKind::Vite)
+    } else {
+        None
+    }
+}
+
+/// 在 `script:<name>` 页面中运行脚本，页面已经存在时重新运行
+fn run_script(browser: &mut Browser, name: &str, spec: RunnerSpec, parser: Option<ParserKind>) {
+    let uri = format!("script:{}", name);
+    browser.add_page(uri.clone());
+    let runner = Runner::new(browser.id().to_owned(), uri.clone(), spec);
+    browser.with_page(&uri, |page| {
+        page.set_title(name.to_owned());
+        page.set_parser(parser.map(ParserKind::create));
+        page.set_runner(runner);
+    });
+    browser.select_page(uri);
+}
+
+fn close(s: &mut Cursive) {
+    if let Some(position) = s.screen_mut().find_layer_from_name(PALETTE_LAYER) {
+        s.screen_mut().remove_layer(position);
+    }
+}
+
+fn update_results(s: &mut Cursive, items: &[PaletteItem], query: &str) {
+    s.call_on_name(PALETTE_LIST, |list: &mut SelectView<usize>| {
+        list.clear();
+        let results = rank(items, query);
+        let width = results
+            .iter()
+            .map(|(index, _)| items[*index].label.width())
+            .max()
+            .unwrap_or(0);
+        for (index, positions) in results {
+            list.add_item(styled_row(&items[index], &positions, width), index);
+        }
+    });
+}
+
+fn submit(s: &mut Cursive, items: &[PaletteItem]) {
+    let selected = s
+        .call_on_name(PALETTE_LIST, |list: &mut SelectView<usize>| {
+            list.selection().map(|index| *index)
+        })
+        .flatten();
+    if let Some(index) = selected {
+        let item = &items[index];
+        close(s);
+        RECENT.lock().unwrap().touch(item.key());
+        (item.callback)(s);
+    }
+}
+
+fn move_selection(s: &mut Cursive, up: bool) {
+    let callback = s.call_on_name(PALETTE_LIST, |list: &mut SelectView<usize>| {
+        if up {
+            list.select_up(1)
+        } else {
+            list.select_down(1)
+        }
+    });
+    if let Some(callback) = callback {
+        callback(s);
+    }
+}
+
+/// 打开或者关闭命令面板：输入文字模糊查找页面、操作与脚本，↑/↓ 选择，Enter 执行
+pub fn toggle(s: &mut Cursive, browser_id: &str) {
+    if s.find_name::<Dialog>(PALETTE_LAYER).is_some() {
+        close(s);
+        return;
+    }
+    let items = match s.call_on_name(browser_id, |browser: &mut Browser| collect_items(browser)) {
+        Some(items) => Rc::new(items),
+        None => return,
+    };
+    let on_edit = items.clone();
+    let on_submit = items.clone();
+    let on_select = items.clone();
+    let content = LinearLayout::vertical()
+        .child(
+            EditView::new()
+                .on_edit(move |s, query, _| update_results(s, &on_edit, query))
+                .on_submit(move |s, _| submit(s, &on_submit))
+                .with_name(PALETTE_INPUT),
+        )
+        .child(
+            SelectView::<usize>::new()
+                .on_submit(move |s, _| submit(s, &on_select))
+                .with_name(PALETTE_LIST)
+                .fixed_height(MAX_RESULTS),
+        );
+    s.add_layer(
+        Dialog::around(content)
+            .title("Command palette")
+            .with_name(PALETTE_LAYER)
+            .wrap_with(OnEventView::new)
+            .on_pre_event(Key::Up, |s| move_selection(s, true))
+            .on_pre_event(Key::Down, |s| move_selection(s, false))
+            .on_event(Key::Esc, close)
+            .fixed_width(72),
+    );
+    update_results(s, &items, "");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(label: &str) -> PaletteItem {
+        PaletteItem {
+            kind: PaletteKind::Action,
+            label: label.to_owned(),
+            detail: String::new(),
+            callback: Rc::new(|_| {}),
+        }
+    }
+
+    fn labels<'a>(items: &'a [PaletteItem], query: &str) -> Vec<&'a str> {
+        rank(items, query)
+            .into_iter()
+            .map(|(index, _)| items[index].label.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn fuzzy_match_finds_characters_in_order() {
+        assert_eq!(fuzzy_match("ne", "Next error").unwrap().1, vec![0, 1]);
+        // 忽略大小写与空白
+        assert_eq!(fuzzy_match("N R", "next error").unwrap().1, vec![0, 6]);
+        assert_eq!(fuzzy_match("cls", "Close").unwrap().1, vec![0, 1, 3]);
+        assert!(fuzzy_match("xn", "Next").is_none());
+        assert!(fuzzy_match("nexts", "Next").is_none());
+    }
+
+    #[test]
+    fn fuzzy_match_prefers_consecutive_and_word_starts() {
+        let score = |pattern, text| fuzzy_match(pattern, text).unwrap().0;
+        assert!(score("nex", "Next error") > score("nex", "Inspect next"));
+        assert!(score("oe", "Open editor") > score("oe", "Close"));
+        assert!(score("pp", "PreviousPage") > score("pp", "Copy path"));
+    }
+
+    #[test]
+    fn empty_query_matches_everything_in_order() {
+        assert_eq!(fuzzy_match("", "anything"), Some((0, Vec::new())));
+        let items = [item("rank:first"), item("rank:second"), item("rank:third")];
+        assert_eq!(
+            labels(&items, ""),
+            ["rank:first", "rank:second", "rank:third"]
+        );
+        assert_eq!(labels(&items, " "), labels(&items, ""));
+    }
+
+    #[test]
+    fn rank_sorts_by_score_and_drops_non_matches() {
+        let items = [
+            item("Inspect status"),
+            item("Debug panel"),
+            item("Stop the runner"),
+        ];
+        assert_eq!(labels(&items, "st"), ["Stop the runner", "Inspect status"]);
+        assert!(rank(&items, "zzz").is_empty());
+        let items: Vec<PaletteItem> = (0..MAX_RESULTS + 5)
+            .map(|index| item(&format!("many {}", index)))
+            .collect();
+        assert_eq!(rank(&items, "many").len(), MAX_RESULTS);
+    }
+
+    #[test]
+    fn rank_puts_recently_used_items_first() {
+        let items = [item("recent:alpha"), item("recent:beta")];
+        assert_eq!(labels(&items, "recent"), ["recent:alpha", "recent:beta"]);
+        RECENT.lock().unwrap().touch(items[1].key());
+        assert_eq!(labels(&items, "recent"), ["recent:beta", "recent:alpha"]);
+        // 得分差距超过奖励时仍然按得分排序
+        assert_eq!(labels(&items, "recent:alpha"), ["recent:alpha"]);
+
+        let mut recent = RecentUse {
+            uses: HashMap::new(),
+            next: 0,
+        };
+        recent.touch("a".to_owned());
+        recent.touch("b".to_owned());
+        assert_eq!(recent.bonus("b"), RECENT_BONUS);
+        assert_eq!(recent.bonus("a"), RECENT_BONUS / 2);
+        assert_eq!(recent.bonus("c"), 0);
+    }
+}
@@ -0,0 +1,522 @@
+use crate::browser::Browser;
+use crate::log_streams::{LogSeparator, DEFAULT_STREAM, DEFAULT_STREAM_ORDER};
+use crate::output_parser::ParserKind;
+use crate::page::Page;
+use crate::page_log;
+use crate::page_tab::TabStatus;
+use crate::runner::{Runner, RunnerSpec};
+use crate::status_bar;
+
+use cursive::utils::markup::StyledString;
+use cursive::CbSink;
+use serde::Deserialize;
+
+use std::io::{BufRead, BufReader, Read};
+use std::thread;
+
+/// 控制协议：每行一个 JSON 对象，`cmd` 字段指定指令，例如
+/// `{"cmd":"write","uri":"tsc","text":"\u001b[32mok\u001b[0m\n"}`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Command {
+    /// 声明客户端的 id，应当作为连接的第一条指令；
+    /// 使用之前的 id 重新连接时会接管它保留下来的页面
+    Hello {
+        client: String,
+        /// 断开时直接关闭页面，而不是保留页面等待重新接入
+        #[serde(default)]
+        close_on_disconnect: bool,
+    },
+    /// 添加页面并选中；页面已经存在时只会选中它
+    AddPage {
+        uri: String,
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        index: Option<usize>,
+        #[serde(default)]
+        parser: Option<ParserKind>,
+    },
+    DelPage {
+        uri: String,
+    },
+    /// 添加 runner 页面并运行命令；页面已经存在时替换它的命令并重新运行
+    Run {
+        uri: String,
+        #[serde(default)]
+        title: Option<String>,
+        #[serde(default)]
+        parser: Option<ParserKind>,
+        #[serde(flatten)]
+        spec: RunnerSpec,
+    },
+    SelectPage {
+        uri: String,
+    },
+    /// 把页面移动到 tab 栏中的指定位置
+    MovePage {
+        uri: String,
+        index: usize,
+    },
+    SetTitle {
+        uri: String,
+        title: String,
+    },
+    /// 设置页面输出的解析器，不传 parser 表示移除
+    SetParser {
+        uri: String,
+        #[serde(default)]
+        parser: Option<ParserKind>,
+    },
+    /// 写入页面的日志流，不指定 stream 时写入默认的日志流；
+    /// separator 设置日志流有内容时显示在它前面的分隔
+    Write {
+        uri: String,
+        text: String,
+        #[serde(default)]
+        stream: Option<String>,
+        #[serde(default)]
+        order: Option<i32>,
+        #[serde(default)]
+        separator: Option<SeparatorSpec>,
+    },
+    ClearLine {
+        uri: String,
+        #[serde(default)]
+        stream: Option<String>,
+    },
+    ClearScreen {
+        uri: String,
+        #[serde(default)]
+        stream: Option<String>,
+    },
+    AddStatus {
+        uri: String,
+        status: TabStatus,
+        reason: String,
+    },
+    DelStatus {
+        uri: String,
+        status: TabStatus,
+        reason: String,
+    },
+    /// 设置状态栏右侧的消息，不传 text 表示清除；
+    /// loading 时在消息前显示 spinner，progress 是 0.0 到 1.0 的进度
+    SetMessage {
+        #[serde(default)]
+        text: Option<String>,
+        #[serde(default)]
+        loading: bool,
+        #[serde(default)]
+        progress: Option<f32>,
+    },
+}
+
+/// 日志流之间的分隔：`"rule"` 是铺满宽度的横线，`{"text":"..."}` 是一行文字
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeparatorSpec {
+    Rule,
+    Text(String),
+}
+
+impl From<SeparatorSpec> for LogSeparator {
+    fn from(spec: SeparatorSpec) -> Self {
+        match spec {
+            SeparatorSpec::Rule => LogSeparator::Rule,
+            SeparatorSpec::Text(text) => LogSeparator::Text(StyledString::plain(text)),
+        }
+    }
+}
+
+impl Command {
+    pub fn parse(line: &str) -> serde_json::Result<Command> {
+        serde_json::from_str(line)
+    }
+    /// 指令操作的页面
+    pub fn uri(&self) -> Option<&str> {
+        match self {
+            Command::AddPage { uri, .. }
+            | Command::DelPage { uri }
+            | Command::Run { uri, .. }
+            | Command::SelectPage { uri }
+            | Command::MovePage { uri, .. }
+            | Command::SetTitle { uri, .. }
+            | Command::SetParser { uri, .. }
+            | Command::Write { uri, .. }
+            | Command::ClearLine { uri, .. }
+            | Command::ClearScreen { uri, .. }
+            | Command::AddStatus { uri, .. }
+            | Command::DelStatus { uri, .. } => Some(uri),
+            Command::Hello { .. } | Command::SetMessage { .. } => None,
+        }
+    }
+
+    pub fn apply(self, browser: &mut Browser, session: &Session) {
+        match self {
+            Command::Hello { .. } => browser.attach_client(&session.client, session.connection),
+            Command::AddPage {
+                uri,
+                title,
+                index,
+                parser,
+            } => {
+                add_page(browser, session, &uri, title, index);
+                if let Some(parser) = parser {
+                    browser.with_page(&uri, |page| page.set_parser(Some(parser.create())));
+                }
+            }
+            Command::Run {
+                uri,
+                title,
+                parser,
+                spec,
+            } => {
+                add_page(browser, session, &uri, title, None);
+                if let Some(parser) = parser {
+                    browser.with_page(&uri, |page| page.set_parser(Some(parser.create())));
+                }
+                let runner = Runner::new(browser.id().to_owned(), uri.clone(), spec);
+                browser.with_page(&uri, |page| page.set_runner(runner));
+            }
+            Command::DelPage { uri } => browser.del_page(uri),
+            Command::SelectPage { uri } => browser.select_page(uri),
+            Command::MovePage { uri, index } => match browser.page_index(&uri) {
+                Some(from) => {
+                    if !browser.move_page(from, index) {
+                        log::warn!("protocol: cannot move {:?} to {}", uri, index);
+                    }
+                }
+                None => log::warn!("protocol: no such page {:?}", uri),
+            },
+            Command::SetTitle { uri, title } => {
+                with_page(browser, &uri, |page| page.set_title(title));
+            }
+            Command::SetParser { uri, parser } => with_page(browser, &uri, |page| {
+                page.set_parser(parser.map(ParserKind::create))
+            }),
+            Command::Write {
+                uri,
+                text,
+                stream,
+                order,
+                separator,
+            } => with_page(browser, &uri, |page| {
+                let order = order.unwrap_or(DEFAULT_STREAM_ORDER);
+                if let Some(separator) = separator {
+                    page.logger(stream.as_deref().unwrap_or(DEFAULT_STREAM), order)
+                        .set_separator(Some(separator.into()));
+                }
+                match stream {
+                    Some(stream) => page.write_stream(&stream, order, &text),
+                    None => page.write(&text),
+                }
+            }),
+            Command::ClearLine { uri, stream } => with_page(browser, &uri, |page| {
+                page.logger(
+                    stream.as_deref().unwrap_or(DEFAULT_STREAM),
+                    DEFAULT_STREAM_ORDER,
+                )
+                .clear_line()
+            }),
+            Command::ClearScreen { uri, stream } => with_page(browser, &uri, |page| {
+                page.logger(
+                    stream.as_deref().unwrap_or(DEFAULT_STREAM),
+                    DEFAULT_STREAM_ORDER,
+                )
+                .clear_screen()
+            }),
+            Command::AddStatus {
+                uri,
+                status,
+                reason,
+            } => with_page(browser, &uri, |page| {
+                page.add_client_status(&session.client, status, reason)
+            }),
+            Command::DelStatus {
+                uri,
+                status,
+                reason,
+            } => with_page(browser, &uri, |page| page.del_client_status(status, reason)),
+            Command::SetMessage {
+                text,
+                loading,
+                progress,
+            } => {
+                status_bar::set_msg(text, loading);
+                status_bar::set_progress(progress);
+            }
+        }
+    }
+}
+
+/// 添加并选中页面，新建的页面归属于当前连接的客户端
+fn add_page(
+    browser: &mut Browser,
+    session: &Session,
+    uri: &str,
+    title: Option<String>,
+    index: Option<usize>,
+) {
+    let created = browser.page_index(uri).is_none();
+    match index {
+        Some(index) => browser.insert_page_at(index, uri.to_owned()),
+        None => browser.add_page(uri.to_owned()),
+    }
+    browser.with_page(uri, |page| {
+        if created {
+            page.set_owner(Some(session.client.clone()));
+        }
+        if let Some(title) = title {
+            page.set_title(title);
+        }
+    });
+}
+
+fn with_page<F: FnOnce(&mut Page)>(browser: &mut Browser, uri: &str, f: F) {
+    if browser.with_page(uri, f).is_none() {
+        log::warn!("protocol: no such page {:?}", uri);
+    }
+}
+
+/// 一个控制协议连接的身份
+#[derive(Debug, Clone)]
+pub struct Session {
+    /// 客户端的 id，页面的所有者；默认按连接生成，可以通过 hello 指令指定
+    pub client: String,
+    /// 连接编号，用于区分同一个客户端先后使用的连接
+    pub connection: usize,
+    pub close_on_disconnect: bool,
+}
+
+impl Session {
+    pub fn new(client: String, connection: usize) -> Self {
+        Session {
+            client,
+            connection,
+            close_on_disconnect: false,
+        }
+    }
+}
+
+/// 逐行读取控制指令，交给 cursive 线程应用到指定的 Browser 上，直到输入结束；
+/// 返回连接最终的身份，界面已经退出时返回 None
+pub fn serve<R: Read>(
+    input: R,
+    cb_sink: &CbSink,
+    browser_id: &str,
+    mut session: Session,
+) -> Option<Session> {
+    send(
+        cb_sink,
+        browser_id,
+        Command::Hello {
+            client: session.client.clone(),
+            close_on_disconnect: session.close_on_disconnect,
+        },
+        &session,
+    )?;
+    for line in BufReader::new(input).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                log::error!("protocol: read failed: {}", err);
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let command = match Command::parse(&line) {
+            Ok(command) => command,
+            Err(err) => {
+                log::warn!("protocol: invalid command {:?}: {}", line, err);
+                continue;
+            }
+        };
+        if let Command::Hello {
+            client,
+            close_on_disconnect,
+        } = &command
+        {
+            // 连接在 hello 之前创建的页面也归属于新的 id
+            let from = std::mem::replace(&mut session.client, client.clone());
+            let to = client.clone();
+            let browser_id = browser_id.to_owned();
+            cb_sink
+                .send(Box::new(move |s| {
+                    s.call_on_name(&browser_id, |browser: &mut Browser| {
+                        browser.transfer_client(&from, &to)
+                    });
+                }))
+                .ok()?;
+            session.close_on_disconnect = *close_on_disconnect;
+        }
+        if let Some(uri) = command.uri() {
+            log::trace!(target: &page_log::target(uri), "{}: {:?}", session.client, command);
+        }
+        send(cb_sink, browser_id, command, &session)?;
+    }
+    Some(session)
+}
+
+fn send(cb_sink: &CbSink, browser_id: &str, command: Command, session: &Session) -> Option<()> {
+    let browser_id = browser_id.to_owned();
+    let session = session.clone();
+    cb_sink
+        .send(Box::new(move |s| {
+            s.call_on_name(&browser_id, |browser: &mut Browser| {
+                command.apply(browser, &session)
+            });
+        }))
+        .ok()
+}
+
+/// 在后台线程中读取 stdin（或者 `--fd` 指定的文件）中的控制指令；
+/// 输入结束（驱动方退出）时关闭界面
+pub fn spawn_reader<R>(input: R, cb_sink: CbSink, browser_id: String)
+where
+    R: 'static + Read + Send,
+{
+    thread::spawn(move || {
+        if serve(
+            input,
+            &cb_sink,
+            &browser_id,
+            Session::new("stdin".to_owned(), 0),
+        )
+        .is_some()
+        {
+            cb_sink.send(Box::new(|s| s.quit())).ok();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Command {
+        Command::parse(line).unwrap()
+    }
+
+    #[test]
+    fn parses_page_commands() {
+        assert!(matches!(
+            parse(r#"{"cmd":"add_page","uri":"tsc","title":"Tsc","index":1}"#),
+            Command::AddPage { uri, title: Some(title), index: Some(1), .. }
+                if uri == "tsc" && title == "Tsc"
+        ));
+        assert!(matches!(
+            parse(r#"{"cmd":"add_page","uri":"tsc"}"#),
+            Command::AddPage {
+                title: None,
+                index: None,
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse(r#"{"cmd":"del_page","uri":"tsc"}"#),
+            Command::DelPage { uri } if uri == "tsc"
+        ));
+        assert!(matches!(
+            parse(r#"{"cmd":"select_page","uri":"tsc"}"#),
+            Command::SelectPage { uri } if uri == "tsc"
+        ));
+        assert!(matches!(
+            parse(r#"{"cmd":"move_page","uri":"tsc","index":0}"#),
+            Command::MovePage { uri, index: 0 } if uri == "tsc"
+        ));
+        assert!(matches!(
+            parse(r#"{"cmd":"set_parser","uri":"tsc","parser":"tsc"}"#),
+            Command::SetParser {
+                parser: Some(ParserKind::Tsc),
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse(r#"{"cmd":"set_parser","uri":"tsc"}"#),
+            Command::SetParser { parser: None, .. }
+        ));
+        assert!(matches!(
+            parse(r#"{"cmd":"set_title","uri":"tsc","title":"类型检查"}"#),
+            Command::SetTitle { title, .. } if title == "类型检查"
+        ));
+    }
+
+    #[test]
+    fn parses_log_commands() {
+        assert!(matches!(
+            parse(r#"{"cmd":"write","uri":"build","text":"\u001b[32mok\u001b[0m\n"}"#),
+            Command::Write { text, stream: None, order: None, separator: None, .. }
+                if text == "\x1b[32mok\x1b[0m\n"
+        ));
+        assert!(matches!(
+            parse(r#"{"cmd":"write","uri":"build","text":"","stream":"vite","order":2,"separator":"rule"}"#),
+            Command::Write { stream: Some(stream), order: Some(2), separator: Some(SeparatorSpec::Rule), .. }
+                if stream == "vite"
+        ));
+        assert!(matches!(
+            parse(r#"{"cmd":"write","uri":"build","text":"","separator":{"text":"vite"}}"#),
+            Command::Write { separator: Some(SeparatorSpec::Text(text)), .. } if text == "vite"
+        ));
+        assert!(matches!(
+            parse(r#"{"cmd":"clear_line","uri":"build"}"#),
+            Command::ClearLine { stream: None, .. }
+        ));
+        assert!(matches!(
+            parse(r#"{"cmd":"clear_screen","uri":"build","stream":"tsc"}"#),
+            Command::ClearScreen { stream: Some(stream), .. } if stream == "tsc"
+        ));
+    }
+
+    #[test]
+    fn parses_status_commands() {
+        assert!(matches!(
+            parse(r#"{"cmd":"add_status","uri":"tsc","status":"error","reason":"src/a.ts"}"#),
+            Command::AddStatus { status: TabStatus::Error, reason, .. } if reason == "src/a.ts"
+        ));
+        assert!(matches!(
+            parse(r#"{"cmd":"del_status","uri":"tsc","status":"loading","reason":"compiling"}"#),
+            Command::DelStatus {
+                status: TabStatus::Loading,
+                ..
+            }
+        ));
+        assert!(matches!(
+            parse(r#"{"cmd":"set_message","text":"watching","loading":true,"progress":0.5}"#),
+            Command::SetMessage { text: Some(text), loading: true, progress: Some(progress) }
+                if text == "watching" && progress == 0.5
+        ));
+        assert!(matches!(
+            parse(r#"{"cmd":"set_message"}"#),
+            Command::SetMessage {
+                text: None,
+                loading: false,
+                progress: None
+            }
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_commands() {
+        // 不是 JSON、不是对象
+        assert!(Command::parse("add_page tsc").is_err());
+        assert!(Command::parse(r#"{"cmd":"add_page","uri":"tsc""#).is_err());
+        assert!(Command::parse(r#""add_page""#).is_err());
+        // 缺少或者未知的指令
+        assert!(Command::parse(r#"{"uri":"tsc"}"#).is_err());
+        assert!(Command::parse(r#"{"cmd":"reload","uri":"tsc"}"#).is_err());
+        // 缺少字段、字段类型或者取值不对
+        assert!(Command::parse(r#"{"cmd":"add_page"}"#).is_err());
+        assert!(Command::parse(r#"{"cmd":"move_page","uri":"tsc","index":-1}"#).is_err());
+        assert!(Command::parse(
+            r#"{"cmd":"add_status","uri":"tsc","status":"fatal","reason":"x"}"#
+        )
+        .is_err());
+        assert!(
+            Command::parse(r#"{"cmd":"write","uri":"tsc","text":"","separator":"dots"}"#).is_err()
+        );
+        assert!(Command::parse(r#"{"cmd":"set_parser","uri":"tsc","parser":"webpack"}"#).is_err());
+    }
+}
@@ -0,0 +1,568 @@
+use crate::browser::Browser;
+use crate::menu::{MenuEntry, MenuSection};
+use crate::page::Page;
+use crate::page_log;
+use crate::page_tab::TabStatus;
+
+use cursive::theme::{BaseColor, Effect, Style};
+use cursive::{CbSink, Vec2};
+use serde::Deserialize;
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+lazy_static! {
+    static ref CB_SINK: Mutex<Option<CbSink>> = Mutex::new(None);
+}
+
+/// 所有 runner 共用的启动序号：页面的 runner 被替换后，
+/// 旧进程迟到的通知也不会与新 runner 的序号相同
+static NEXT_GENERATION: AtomicUsize = AtomicUsize::new(1);
+
+/// 重启时等待旧进程组退出的时间，超时后发送 SIGKILL
+const STOP_TIMEOUT: Duration = Duration::from_secs(2);
+/// 进程退出后等待输出读完的时间：进程留下的子进程可能还持有输出管道
+const READER_TIMEOUT: Duration = Duration::from_millis(500);
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// 子进程的输出与退出都在后台线程中等待，需要通过 cb_sink 回到 cursive 线程更新页面
+pub fn init(cb_sink: CbSink) {
+    *CB_SINK.lock().unwrap() = Some(cb_sink);
+}
+
+type RunnerAction = fn(&mut Runner, &Page);
+
+/// 要运行的命令，例如 `tsc --build -w`、`vite build --watch`、`yarn install`
+#[derive(Debug, Clone, Deserialize)]
+pub struct RunnerSpec {
+    pub program: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+    /// 追加到继承的环境变量上
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// 在伪终端中运行：vite、yarn、tsc 检测到 isatty 时才会输出颜色与进度；
+    /// 此时 stdout 与 stderr 合并在一起，不再区分显示
+    #[serde(default)]
+    pub pty: bool,
+}
+
+impl RunnerSpec {
+    pub fn command_line(&self) -> String {
+        let mut line = self.program.clone();
+        for arg in &self.args {
+            line.push(' ');
+            line.push_str(arg);
+        }
+        line
+    }
+}
+
+/// 页面中运行的子进程：输出写入页面的默认日志流，stderr 显示为红色，
+/// tab 状态反映进程是在运行中、正常退出还是出错退出
+pub struct Runner {
+    browser_id: String,
+    uri: String,
+    spec: RunnerSpec,
+    /// 每次启动时从全局序号中取得，旧进程迟到的输出与退出通知会被忽略
+    generation: usize,
+    process: Option<Process>,
+    /// 当前显示在 tab 上的状态
+    status: Option<(TabStatus, String)>,
+    /// 伪终端的主端，进程退出后关闭
+    pty: Option<OwnedFd>,
+    /// 页面内容区域的大小，也是伪终端的窗口大小
+    size: Vec2,
+}
+
+impl Runner {
+    pub fn new(browser_id: String, uri: String, spec: RunnerSpec) -> Self {
+        Runner {
+            browser_id,
+            uri,
+            spec,
+            generation: 0,
+            process: None,
+            status: None,
+            pty: None,
+            size: DEFAULT_PTY_SIZE,
+        }
+    }
+    pub fn spec(&self) -> &RunnerSpec {
+        &self.spec
+    }
+    /// runner 页面在菜单栏 Runner 菜单中的操作
+    pub fn menu_entries(&self) -> Vec<MenuEntry> {
+        let actions: [(&str, &str, RunnerAction); 3] = [
+            ("Restart", "Ctrl+R", |runner, page| runner.start(page)),
+            ("Stop", "Ctrl+T", |runner, _| runner.stop()),
+            ("Kill", "Ctrl+K", |runner, _| runner.kill()),
+        ];
+        actions
+            .into_iter()
+            .map(|(label, shortcut, action)| {
+                let browser_id = self.browser_id.clone();
+                let uri = self.uri.clone();
+                MenuEntry::new(MenuSection::Runner, label, move |s| {
+                    s.call_on_name(&browser_id, |browser: &mut Browser| {
+                        browser.with_page(&uri, |page| page.with_runner(action))
+                    });
+                })
+                .with_shortcut(shortcut)
+            })
+            .collect()
+    }
+
+    /// 启动进程；已经在运行时先结束旧的进程组并等待它退出，即重启
+    pub fn start(&mut self, page: &Page) {
+        if let Some(process) = self.process.take() {
+            process.terminate(STOP_TIMEOUT);
+        }
+        self.generation = NEXT_GENERATION.fetch_add(1, Ordering::Relaxed);
+        self.pty = None;
+        page.reset_parser();
+        page.write_styled(
+            &format!("$ {}\n", self.spec.command_line()),
+            Style::from(Effect::Bold),
+        );
+
+        let mut command = Command::new(&self.spec.program);
+        command.args(&self.spec.args).envs(&self.spec.env);
+        if let Some(cwd) = &self.spec.cwd {
+            command.current_dir(cwd);
+        }
+        let spawned = if self.spec.pty {
+            self.spawn_pty(&mut command)
+        } else {
+            command
+                .stdin(Stdio::null())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                // 独立的进程组，停止时连同它启动的子进程一起结束
+                .process_group(0)
+                .spawn()
+        };
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(err) => {
+                let reason = format!("failed to start: {}", err);
+                log::error!(target: &page_log::target(&self.uri), "{}: {}", self.spec.command_line(), reason);
+                page.write_styled(&format!("{}\n", reason), BaseColor::Red.light().into());
+                self.set_status(page, Some((TabStatus::Error, reason)));
+                return;
+            }
+        };
+        let process = Process::new(child.id());
+        let exited = process.exited.clone();
+        self.process = Some(process);
+        log::info!(
+            target: &page_log::target(&self.uri),
+            "started `{}` (pid {})",
+            self.spec.command_line(),
+            child.id()
+        );
+        // 运行中只是静态的提示，Loading（spinner）留给解析器表示编译、构建中
+        self.set_status(page, Some((TabStatus::Info, "running".to_owned())));
+
+        let pty_reader = self
+            .pty
+            .as_ref()
+            .and_then(|master| master.try_clone().ok())
+            .map(|master| self.spawn_reader(File::from(master), false));
+        let readers = [
+            child.stdout.take().map(|out| self.spawn_reader(out, false)),
+            child.stderr.take().map(|err| self.spawn_reader(err, true)),
+            pty_reader,
+        ];
+        let generation = self.generation;
+        let browser_id = self.browser_id.clone();
+        let uri = self.uri.clone();
+        thread::spawn(move || {
+            let status = child.wait();
+            exited.store(true, Ordering::SeqCst);
+            // 尽量等输出读完再报告退出，保证退出信息显示在最后
+            let deadline = Instant::now() + READER_TIMEOUT;
+            for reader in readers.iter().flatten() {
+                while !reader.is_finished() && Instant::now() < deadline {
+                    thread::sleep(POLL_INTERVAL);
+                }
+            }
+            if let Ok(status) = status {
+                with_runner(browser_id, uri, move |runner, page| {
+                    runner.on_exit(page, generation, status)
+                });
+            }
+        });
+    }
+
+    fn spawn_reader<R>(&self, mut input: R, is_stderr: bool) -> thread::JoinHandle<()>
+    where
+        R: 'static + Read + Send,
+    {
+        let generation = self.generation;
+        let browser_id = self.browser_id.clone();
+        let uri = self.uri.clone();
+        thread::spawn(move || {
+            let mut buf = [0u8; 8192];
+            let mut pending = Vec::new();
+            while let Ok(len) = input.read(&mut buf) {
+                if len == 0 {
+                    break;
+                }
+                pending.extend_from_slice(&buf[..len]);
+                let text = take_utf8(&mut pending);
+                if text.is_empty() {
+                    continue;
+                }
+                let sent = with_runner(browser_id.clone(), uri.clone(), move |runner, page| {
+                    runner.on_output(page, generation, &text, is_stderr)
+                });
+                if !sent {
+                    break;
+                }
+            }
+        })
+    }
+
+    fn on_output(&mut self, page: &Page, generation: usize, text: &str, is_stderr: bool) {
+        if generation != self.generation {
+            return;
+        }
+        if is_stderr {
+            page.write_styled(text, BaseColor::Red.light().into());
+        } else {
+            page.write(text);
+        }
+    }
+
+    fn on_exit(&mut self, page: &Page, generation: usize, status: ExitStatus) {
+        if generation != self.generation {
+            return;
+        }
+        self.process = None;
+        self.pty = None;
+        let (tab_status, reason) = match (status.code(), status.signal()) {
+            (Some(0), _) => (TabStatus::Success, "exited with code 0".to_owned()),
+            (Some(code), _) => (TabStatus::Error, format!("exited with code {}", code)),
+            (None, Some(signal)) => (
+                TabStatus::Error,
+                format!("terminated by {}", signal_name(signal)),
+            ),
+            (None, None) => (TabStatus::Error, "exited".to_owned()),
+        };
+        log::info!(target: &page_log::target(&self.uri), "{}", reason);
+        page.write_styled(&format!("[{}]\n", reason), tab_status.style());
+        self.set_status(page, Some((tab_status, reason)));
+    }
+
+    /// 在新的伪终端中启动进程：子进程成为新会话的首进程，伪终端是它的控制终端，
+    /// 进程组与会话相同，所以停止时仍然可以向整个进程组发送信号
+    fn spawn_pty(&mut self, command: &mut Command) -> io::Result<std::process::Child> {
+        let (master, slave) = open_pty(self.size)?;
+        command
+            .stdin(Stdio::from(slave.try_clone()?))
+            .stdout(Stdio::from(slave.try_clone()?))
+            .stderr(Stdio::from(slave));
+        unsafe {
+            command.pre_exec(|| {
+                if libc::setsid() == -1 || libc::ioctl(0, libc::TIOCSCTTY, 0) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        let child = command.spawn()?;
+        self.pty = Some(master);
+        Ok(child)
+    }
+
+    /// 页面内容区域的大小变化时同步到伪终端，内核会向前台进程组发送 SIGWINCH
+    pub fn resize(&mut self, size: Vec2) {
+        if size == self.size || size.x == 0 || size.y == 0 {
+            return;
+        }
+        self.size = size;
+        if let Some(master) = &self.pty {
+            let winsize = to_winsize(size);
+            unsafe {
+                libc::ioctl(master.as_raw_fd(), libc::TIOCSWINSZ, &winsize);
+            }
+        }
+    }
+
+    /// 移除 runner 显示在 tab 上的状态
+    pub fn clear_status(&mut self, page: &Page) {
+        self.set_status(page, None);
+    }
+    fn set_status(&mut self, page: &Page, status: Option<(TabStatus, String)>) {
+        let mut tab = page.tab.borrow_mut();
+        if let Some((old_status, old_reason)) = self.status.take() {
+            tab.del_status(old_status, old_reason);
+        }
+        if let Some((new_status, new_reason)) = &status {
+            tab.add_status(*new_status, new_reason.clone());
+        }
+        self.status = status;
+    }
+
+    /// 向整个进程组发送信号，进程已经退出时不再发送
+    pub fn signal(&self, signal: i32) {
+        if let Some(process) = self
+            .process
+            .as_ref()
+            .filter(|process| !process.has_exited())
+        {
+            log::debug!(
+                target: &page_log::target(&self.uri),
+                "sending {} to process group {}",
+                signal_name(signal),
+                process.pid
+            );
+            process.signal(signal);
+        }
+    }
+    pub fn stop(&self) {
+        self.signal(libc::SIGTERM);
+    }
+    pub fn kill(&self) {
+        self.signal(libc::SIGKILL);
+    }
+}
+
+impl Drop for Runner {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// 启动的进程，它的进程号也是进程组号
+struct Process {
+    pid: u32,
+    /// 等待进程的线程在 wait() 返回后立即设置，之后进程号可能被复用
+    exited: Arc<AtomicBool>,
+}
+
+impl Process {
+    fn new(pid: u32) -> Self {
+        Process {
+            pid,
+            exited: Arc::new(AtomicBool::new(false)),
+        }
+    }
+    fn has_exited(&self) -> bool {
+        self.exited.load(Ordering::SeqCst)
+    }
+    fn signal(&self, signal: i32) {
+        unsafe {
+            libc::kill(-(self.pid as i32), signal);
+        }
+    }
+    /// 进程组中是否还有进程
+    fn group_alive(&self) -> bool {
+        unsafe { libc::kill(-(self.pid as i32), 0) == 0 }
+    }
+    /// 等待进程退出、进程组中的其它进程也都结束，超时返回 false
+    fn wait_exit(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while !self.has_exited() || self.group_alive() {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+        true
+    }
+    /// 结束整个进程组：先发送 SIGTERM，超时后发送 SIGKILL
+    fn terminate(&self, timeout: Duration) {
+        self.signal(libc::SIGTERM);
+        if !self.wait_exit(timeout) {
+            self.signal(libc::SIGKILL);
+            self.wait_exit(timeout);
+        }
+    }
+}
+
+/// 还没有布局时伪终端使用的大小
+const DEFAULT_PTY_SIZE: Vec2 = Vec2 { x: 80, y: 24 };
+
+fn to_winsize(size: Vec2) -> libc::winsize {
+    libc::winsize {
+        ws_row: size.y.min(u16::MAX as usize) as u16,
+        ws_col: size.x.min(u16::MAX as usize) as u16,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    }
+}
+
+/// 打开一对伪终端，返回 (主端, 从端)
+fn open_pty(size: Vec2) -> io::Result<(OwnedFd, OwnedFd)> {
+    let mut master = 0;
+    let mut slave = 0;
+    let winsize = to_winsize(size);
+    let result = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            &winsize,
+        )
+    };
+    if result == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    unsafe {
+        // 子进程不应该继承主端
+        libc::fcntl(master, libc::F_SETFD, libc::FD_CLOEXEC);
+        Ok((OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)))
+    }
+}
+
+/// 回到 cursive 线程，找到页面上的 runner 并执行 f；界面已经退出时返回 false
+fn with_runner<F>(browser_id: String, uri: String, f: F) -> bool
+where
+    F: 'static + Send + FnOnce(&mut Runner, &Page),
+{
+    let cb_sink = match CB_SINK.lock().unwrap().clone() {
+        Some(cb_sink) => cb_sink,
+        None => return false,
+    };
+    cb_sink
+        .send(Box::new(move |s| {
+            s.call_on_name(&browser_id, |browser: &mut Browser| {
+                browser.with_page(&uri, |page| page.with_runner(f))
+            });
+        }))
+        .is_ok()
+}
+
+/// 取出 pending 中完整的 UTF-8 文本，被截断在末尾的多字节字符留到下一次
+fn take_utf8(pending: &mut Vec<u8>) -> String {
+    let valid = match std::str::from_utf8(pending) {
+        Ok(_) => pending.len(),
+        Err(err) if err.error_len().is_none() => err.valid_up_to(),
+        Err(_) => pending.len(),
+    };
+    let rest = pending.split_off(valid);
+    let text = String::from_utf8_lossy(pending).into_owned();
+    *pending = rest;
+    text
+}
+
+fn signal_name(signal: i32) -> String {
+    match signal {
+        libc::SIGHUP => "SIGHUP".to_owned(),
+        libc::SIGINT => "SIGINT".to_owned(),
+        libc::SIGQUIT => "SIGQUIT".to_owned(),
+        libc::SIGABRT => "SIGABRT".to_owned(),
+        libc::SIGKILL => "SIGKILL".to_owned(),
+        libc::SIGSEGV => "SIGSEGV".to_owned(),
+        libc::SIGPIPE => "SIGPIPE".to_owned(),
+        libc::SIGTERM => "SIGTERM".to_owned(),
+        signal => format!("signal {}", signal),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn runner() -> Runner {
+        Runner::new(
+            "browser".to_owned(),
+            "test".to_owned(),
+            RunnerSpec {
+                program: "sh".to_owned(),
+                args: Vec::new(),
+                cwd: None,
+                env: HashMap::new(),
+                pty: true,
+            },
+        )
+    }
+
+    /// 在 runner 的伪终端中运行脚本，读取全部输出直到从端关闭
+    fn run_in_pty<F: FnOnce(&mut Runner)>(runner: &mut Runner, script: &str, f: F) -> String {
+        let mut command = Command::new("sh");
+        command.args(["-c", script]);
+        let mut child = runner.spawn_pty(&mut command).unwrap();
+        // Command 持有从端的副本，不释放的话读不到结束
+        drop(command);
+        f(runner);
+        let master = runner.pty.as_ref().unwrap().try_clone().unwrap();
+        let mut output = Vec::new();
+        // 从端全部关闭后读取主端返回 EIO，此前读到的内容仍然保留
+        File::from(master).read_to_end(&mut output).ok();
+        child.wait().unwrap();
+        String::from_utf8_lossy(&output).trim().to_owned()
+    }
+
+    /// 在独立的进程组中启动脚本，等它输出第一行后像 Runner 一样在后台线程中等待它退出
+    fn spawn_group(script: &str) -> (Process, thread::JoinHandle<ExitStatus>) {
+        let mut child = Command::new("sh")
+            .args(["-c", script])
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .spawn()
+            .unwrap();
+        let mut stdout = child.stdout.take().unwrap();
+        let mut byte = [0u8];
+        while stdout.read(&mut byte).unwrap() == 1 && byte[0] != b'\n' {}
+        let process = Process::new(child.id());
+        let exited = process.exited.clone();
+        let waiter = thread::spawn(move || {
+            let status = child.wait().unwrap();
+            exited.store(true, Ordering::SeqCst);
+            status
+        });
+        (process, waiter)
+    }
+
+    #[test]
+    fn terminate_waits_for_the_group_to_exit() {
+        let (process, waiter) = spawn_group("echo ready; exec sleep 30");
+        process.terminate(Duration::from_secs(5));
+        assert!(process.has_exited());
+        assert!(!process.group_alive());
+        assert_eq!(waiter.join().unwrap().signal(), Some(libc::SIGTERM));
+    }
+
+    #[test]
+    fn terminate_kills_a_group_that_ignores_sigterm() {
+        let (process, waiter) = spawn_group("trap '' TERM; echo ready; exec sleep 30");
+        let started = Instant::now();
+        process.terminate(Duration::from_millis(200));
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert!(process.has_exited());
+        assert_eq!(waiter.join().unwrap().signal(), Some(libc::SIGKILL));
+    }
+
+    #[test]
+    fn child_sees_a_terminal_of_the_runner_size() {
+        let mut runner = runner();
+        let output = run_in_pty(&mut runner, "test -t 1 && stty size", |_| {});
+        assert_eq!(output, "24 80");
+    }
+
+    #[test]
+    fn resize_updates_the_running_terminal() {
+        let mut runner = runner();
+        let output = run_in_pty(
+            &mut runner,
+            "test -t 1 && sleep 0.5 && stty size",
+            |runner| runner.resize(Vec2::new(100, 30)),
+        );
+        assert_eq!(output, "30 100");
+    }
+}
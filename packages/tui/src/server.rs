@@ -0,0 +1,55 @@
+use crate::browser::Browser;
+use crate::protocol::{self, Session};
+
+use cursive::CbSink;
+
+use std::io;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::Path;
+use std::thread;
+
+/// 在 Unix socket 上接受多个客户端的控制协议连接，每个连接使用独立的线程读取指令；
+/// bfsw 下的每个 bfsp 构建都可以连接进来，把自己的页面汇报到同一个界面上
+pub fn spawn_server(path: &Path, cb_sink: CbSink, browser_id: String) -> io::Result<()> {
+    // 上一次异常退出时遗留的 socket 文件；路径上是其它文件时不删除，交给 bind 报错
+    let is_socket = std::fs::metadata(path).is_ok_and(|meta| meta.file_type().is_socket());
+    if is_socket && UnixStream::connect(path).is_err() {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    log::info!("server: listening on {}", path.display());
+
+    thread::spawn(move || {
+        for (connection, stream) in (1..).zip(listener.incoming()) {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    log::error!("server: accept failed: {}", err);
+                    continue;
+                }
+            };
+            let cb_sink = cb_sink.clone();
+            let browser_id = browser_id.clone();
+            thread::spawn(move || {
+                let session = Session::new(format!("connection-{}", connection), connection);
+                log::info!("server: {} connected", session.client);
+                if let Some(session) = protocol::serve(stream, &cb_sink, &browser_id, session) {
+                    log::info!("server: {} disconnected", session.client);
+                    cb_sink
+                        .send(Box::new(move |s| {
+                            s.call_on_name(&browser_id, |browser: &mut Browser| {
+                                browser.detach_client(
+                                    &session.client,
+                                    session.connection,
+                                    session.close_on_disconnect,
+                                )
+                            });
+                        }))
+                        .ok();
+                }
+            });
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use cursive::CbSink;
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// 与 TS 版本 `const.mts` 中的 FRAMES 保持一致
+pub const FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const FRAME_INTERVAL: Duration = Duration::from_millis(80);
+
+struct TickerState {
+    cb_sink: Option<CbSink>,
+    /// 正在转动的 spinner 数量，为 0 时不再触发重绘
+    active: usize,
+}
+
+lazy_static! {
+    static ref START: Instant = Instant::now();
+    static ref TICKER: (Mutex<TickerState>, Condvar) = (
+        Mutex::new(TickerState {
+            cb_sink: None,
+            active: 0,
+        }),
+        Condvar::new()
+    );
+}
+
+/// 启动动画时钟：只要还有 spinner 在转动，就定时通过 cb_sink 唤醒 cursive 重绘
+pub fn init(cb_sink: CbSink) {
+    let (state, wakeup) = &*TICKER;
+    state.lock().unwrap().cb_sink = Some(cb_sink);
+    wakeup.notify_all();
+
+    thread::spawn(|| {
+        let (state, wakeup) = &*TICKER;
+        loop {
+            let cb_sink = {
+                let mut state = state.lock().unwrap();
+                while state.active == 0 || state.cb_sink.is_none() {
+                    state = wakeup.wait(state).unwrap();
+                }
+                state.cb_sink.clone().unwrap()
+            };
+            // 空的回调也会让 cursive 认为有事件发生，从而刷新界面
+            if cb_sink.send(Box::new(|_| {})).is_err() {
+                break;
+            }
+            thread::sleep(FRAME_INTERVAL);
+        }
+    });
+}
+
+/// 当前帧，所有 spinner 共用同一个时钟，保持同步转动
+pub fn frame() -> &'static str {
+    let index = START.elapsed().as_millis() / FRAME_INTERVAL.as_millis();
+    FRAMES[index as usize % FRAMES.len()]
+}
+
+/// 持有期间动画时钟保持运转，drop 后自动释放
+pub struct SpinnerGuard(());
+
+impl SpinnerGuard {
+    pub fn new() -> Self {
+        let (state, wakeup) = &*TICKER;
+        state.lock().unwrap().active += 1;
+        wakeup.notify_all();
+        SpinnerGuard(())
+    }
+}
+
+impl Drop for SpinnerGuard {
+    fn drop(&mut self) {
+        let (state, _) = &*TICKER;
+        state.lock().unwrap().active -= 1;
+    }
+}
@@ -0,0 +1,162 @@
+use crate::page_tab::PageTab;
+use crate::spinner::{self, SpinnerGuard};
+use crate::utils::truncate_str;
+use core::cell::RefCell;
+
+use cursive::theme::{ColorStyle, Effect};
+use cursive::view::View;
+use cursive::{CbSink, Printer, Vec2};
+
+use std::rc::Rc;
+use std::sync::Mutex;
+use unicode_width::UnicodeWidthStr;
+
+/// 进度条的格数
+const PROGRESS_WIDTH: usize = 10;
+
+/// `████░░░░░░  42%`
+fn progress_text(progress: f32) -> String {
+    let filled = (progress * PROGRESS_WIDTH as f32).round() as usize;
+    format!(
+        "{}{} {:>3}%",
+        "█".repeat(filled),
+        "░".repeat(PROGRESS_WIDTH - filled),
+        (progress * 100.0).round() as usize
+    )
+}
+
+/// 状态栏右侧的全局状态，对应 TS 版本 `StatusBar.setMsg(msg, loading)`
+struct GlobalStatus {
+    cb_sink: Option<CbSink>,
+    message: Option<String>,
+    /// 显示 spinner 期间持有，保持动画运转
+    spinner: Option<SpinnerGuard>,
+    progress: Option<f32>,
+}
+
+lazy_static! {
+    static ref STATUS: Mutex<GlobalStatus> = Mutex::new(GlobalStatus {
+        cb_sink: None,
+        message: None,
+        spinner: None,
+        progress: None,
+    });
+}
+
+/// 状态栏可以在任意线程中更新，更新后通过 cb_sink 唤醒 cursive 重绘
+pub fn init(cb_sink: CbSink) {
+    STATUS.lock().unwrap().cb_sink = Some(cb_sink);
+}
+
+fn update<F: FnOnce(&mut GlobalStatus)>(f: F) {
+    let mut status = STATUS.lock().unwrap();
+    f(&mut status);
+    if let Some(cb_sink) = &status.cb_sink {
+        cb_sink.send(Box::new(|_| {})).ok();
+    }
+}
+
+/// 设置状态栏右侧的消息，None 表示清除；loading 时在消息前显示 spinner
+pub fn set_msg(message: Option<String>, loading: bool) {
+    update(|status| {
+        status.message = message;
+        if !loading {
+            status.spinner = None;
+        } else if status.spinner.is_none() {
+            status.spinner = Some(SpinnerGuard::new());
+        }
+    });
+}
+
+/// 设置状态栏右侧的进度，0.0 到 1.0，None 表示不显示
+pub fn set_progress(progress: Option<f32>) {
+    update(|status| status.progress = progress.map(|progress| progress.clamp(0.0, 1.0)));
+}
+
+/// `⠋ message ████░░░░░░  42%`
+fn global_text() -> Option<String> {
+    let status = STATUS.lock().unwrap();
+    let mut parts = Vec::new();
+    if status.spinner.is_some() {
+        parts.push(spinner::frame().to_owned());
+    }
+    if let Some(message) = &status.message {
+        parts.push(message.clone());
+    }
+    if let Some(progress) = status.progress {
+        parts.push(progress_text(progress));
+    }
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" "))
+    }
+}
+
+/// Browser 底部的状态栏，左侧显示选中页面最严重状态的原因与进度，
+/// 右侧显示全局的消息、spinner 与进度
+pub struct BrowserStatusBarViewer {
+    tab: Rc<RefCell<Option<Rc<RefCell<PageTab>>>>>,
+}
+
+impl Clone for BrowserStatusBarViewer {
+    fn clone(&self) -> BrowserStatusBarViewer {
+        BrowserStatusBarViewer {
+            tab: self.tab.clone(),
+        }
+    }
+}
+
+impl BrowserStatusBarViewer {
+    pub fn new() -> Self {
+        BrowserStatusBarViewer {
+            tab: Rc::new(RefCell::new(None)),
+        }
+    }
+    pub fn set_tab(self: &mut BrowserStatusBarViewer, tab: Option<Rc<RefCell<PageTab>>>) {
+        *self.tab.borrow_mut() = tab;
+    }
+}
+
+impl View for BrowserStatusBarViewer {
+    fn draw(&self, printer: &Printer) {
+        printer.with_color(ColorStyle::secondary(), |printer| {
+            printer.print_hline((0, 0), printer.size.x, " ");
+        });
+        // 全局状态靠右显示，最多占一半的宽度
+        let mut right = printer.size.x;
+        if let Some(message) = global_text() {
+            let message = truncate_str(&message, printer.size.x / 2);
+            right = printer.size.x.saturating_sub(message.width() + 1);
+            printer.with_color(ColorStyle::secondary(), |printer| {
+                printer.print((right, 0), &message);
+            });
+        }
+        let tab = self.tab.borrow();
+        let tab = match &*tab {
+            Some(tab) => tab.borrow(),
+            None => return,
+        };
+        if let Some(progress) = tab.progress() {
+            let text = progress_text(progress);
+            right = right.saturating_sub(text.width() + 1);
+            printer.with_color(ColorStyle::secondary(), |printer| {
+                printer.print((right, 0), &text);
+            });
+        }
+        if let Some((status, reason)) = tab.top_reason() {
+            let symbol = status.symbol();
+            printer.with_style(status.style(), |printer| {
+                printer.print((1, 0), symbol);
+            });
+            let offset = 1 + symbol.width() + 1;
+            let reason = truncate_str(reason, right.saturating_sub(offset + 1));
+            printer.with_effect(Effect::Dim, |printer| {
+                printer.print((offset, 0), &reason);
+            });
+        }
+    }
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        Vec2::new(constraint.x, 1)
+    }
+}
@@ -1,17 +1,66 @@
 use crate::page_tab::PageTab;
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 
-use cursive::view::{Resizable, SizeConstraint, View};
+use cursive::theme::{BaseColor, ColorStyle, Effect};
+use cursive::view::View;
 
-use cursive::views::{LinearLayout, ResizedView};
 use cursive::{Printer, Vec2};
 
-use std::cmp::max;
+use std::cmp::{max, min};
 use std::rc::Rc;
 
+const SPLITER: &str = "|";
+const ARROW_LEFT: &str = "◀";
+const ARROW_RIGHT: &str = "▶";
+/// tab 的最小宽度，再窄就无法辨认了，此时改为滚动显示
+const MIN_TAB_WIDTH: usize = 8;
+
+/// tab 宽度的分配策略，通过 `--tab-width even|proportional` 指定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabWidthPolicy {
+    /// 平分 tab 栏的宽度
+    Even,
+    /// 按标题的长度分配宽度
+    Proportional,
+}
+
+impl TabWidthPolicy {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "even" => Some(TabWidthPolicy::Even),
+            "proportional" => Some(TabWidthPolicy::Proportional),
+            _ => None,
+        }
+    }
+}
+
+/// tab 栏上被鼠标命中的区域
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TabBarHit {
+    Tab(usize),
+    ScrollLeft,
+    ScrollRight,
+}
+
+/// tab 栏在某个宽度下的布局结果，draw 与鼠标命中测试共用
+pub struct TabStripLayout {
+    /// 每个 tab 的区域（起点, 宽度），不包含分隔符；滚动出可视区域的 tab 为 None
+    pub ranges: Vec<Option<(usize, usize)>>,
+    /// 左侧还有被隐藏的 tab
+    pub overflow_left: bool,
+    /// 右侧还有被隐藏的 tab
+    pub overflow_right: bool,
+}
+
 // #[derive(Clone)]
 pub struct BrowserTabBarViewer {
     tabs: Rc<RefCell<Vec<Rc<RefCell<PageTab>>>>>,
+    selected: Rc<Cell<Option<usize>>>,
+    /// 最近一次布局时的宽度，鼠标命中测试需要用到
+    width: Rc<Cell<usize>>,
+    /// 溢出时第一个可见的 tab
+    scroll: Rc<Cell<usize>>,
+    width_policy: Rc<Cell<TabWidthPolicy>>,
     // view: Rc<RefCell<ResizedView<LinearLayout>>>,
     // width: usize,
 }
@@ -20,6 +69,10 @@ impl Clone for BrowserTabBarViewer {
     fn clone(&self) -> BrowserTabBarViewer {
         BrowserTabBarViewer {
             tabs: self.tabs.clone(),
+            selected: self.selected.clone(),
+            width: self.width.clone(),
+            scroll: self.scroll.clone(),
+            width_policy: self.width_policy.clone(),
         }
     }
 }
@@ -29,14 +82,161 @@ impl BrowserTabBarViewer {
         // let width: usize = 10;
         BrowserTabBarViewer {
             tabs: Rc::new(RefCell::new(Vec::new())),
+            selected: Rc::new(Cell::new(None)),
+            width: Rc::new(Cell::new(0)),
+            scroll: Rc::new(Cell::new(0)),
+            width_policy: Rc::new(Cell::new(TabWidthPolicy::Even)),
             // view: Rc::new(RefCell::new(
             //     LinearLayout::horizontal().fixed_size(cursive::XY::new(width, 1)),
             // )),
             // width,
         }
     }
-    pub fn add_tab(self: &mut BrowserTabBarViewer, tab: Rc<RefCell<PageTab>>) {
-        self.tabs.borrow_mut().push(tab);
+    pub fn set_tabs(self: &mut BrowserTabBarViewer, tabs: Vec<Rc<RefCell<PageTab>>>) {
+        *self.tabs.borrow_mut() = tabs;
+    }
+    pub fn set_selected(self: &mut BrowserTabBarViewer, selected: Option<usize>) {
+        self.selected.set(selected);
+    }
+    pub fn set_width_policy(self: &mut BrowserTabBarViewer, policy: TabWidthPolicy) {
+        self.width_policy.set(policy);
+    }
+    /// 前 9 个 tab 可以通过数字键直接切换，在 tab 上显示对应的快捷键
+    pub fn shortcut_hint(index: usize) -> Option<String> {
+        if index < 9 {
+            Some(format!("[{}]", index + 1))
+        } else {
+            None
+        }
+    }
+    fn hint_width(index: usize) -> usize {
+        Self::shortcut_hint(index).map_or(0, |hint| hint.len() + 1)
+    }
+    /// 完整显示 tab（快捷键提示 + 图标 + 标题）所需的宽度
+    fn natural_widths(&self) -> Vec<usize> {
+        self.tabs
+            .borrow()
+            .iter()
+            .enumerate()
+            .map(|(i, tab)| Self::hint_width(i) + tab.borrow().natural_width())
+            .collect()
+    }
+    /// 计算 tab 栏在指定宽度下的布局；放不下时保留两侧的箭头，并滚动到选中的 tab 可见
+    pub fn strip_layout(&self, width: usize) -> TabStripLayout {
+        let tabs_count = self.tabs.borrow().len();
+        let natural_widths = self.natural_widths();
+        let spliters_width = tabs_count.saturating_sub(1) * SPLITER.len();
+        let content_width = width.saturating_sub(spliters_width);
+
+        let widths: Vec<usize> = match self.width_policy.get() {
+            TabWidthPolicy::Even => {
+                vec![content_width / max(tabs_count, 1); tabs_count]
+            }
+            TabWidthPolicy::Proportional => {
+                let natural_total = max(natural_widths.iter().sum::<usize>(), 1);
+                natural_widths
+                    .iter()
+                    .map(|natural| content_width * natural / natural_total)
+                    .collect()
+            }
+        };
+        if widths.iter().all(|&w| w >= MIN_TAB_WIDTH) {
+            self.scroll.set(0);
+            let mut walk_size = 0;
+            let ranges = widths
+                .into_iter()
+                .enumerate()
+                .map(|(i, w)| {
+                    if i != 0 {
+                        walk_size += SPLITER.len();
+                    }
+                    let range = (walk_size, w);
+                    walk_size += w;
+                    Some(range)
+                })
+                .collect();
+            return TabStripLayout {
+                ranges,
+                overflow_left: false,
+                overflow_right: false,
+            };
+        }
+
+        // 溢出：每个 tab 使用自身的宽度（不小于最小宽度），两侧各留一格显示箭头
+        let widths: Vec<usize> = match self.width_policy.get() {
+            TabWidthPolicy::Even => vec![MIN_TAB_WIDTH; tabs_count],
+            TabWidthPolicy::Proportional => natural_widths
+                .iter()
+                .map(|&w| max(w, MIN_TAB_WIDTH))
+                .collect(),
+        };
+        let strip_start = 1;
+        let strip_width = width.saturating_sub(2);
+        let visible_end = |scroll: usize| {
+            let mut walk_size = 0;
+            let mut end = scroll;
+            while end < tabs_count {
+                let spliter_len = if end == scroll { 0 } else { SPLITER.len() };
+                if walk_size + spliter_len + widths[end] > strip_width {
+                    break;
+                }
+                walk_size += spliter_len + widths[end];
+                end += 1;
+            }
+            // 至少显示一个 tab，哪怕它会被截断
+            max(end, min(scroll + 1, tabs_count))
+        };
+        let mut scroll = min(self.scroll.get(), tabs_count.saturating_sub(1));
+        if let Some(selected) = self.selected.get() {
+            if selected < scroll {
+                scroll = selected;
+            }
+            while selected >= visible_end(scroll) {
+                scroll += 1;
+            }
+        }
+        self.scroll.set(scroll);
+
+        let end = visible_end(scroll);
+        let mut walk_size = strip_start;
+        let ranges = (0..tabs_count)
+            .map(|i| {
+                if i < scroll || i >= end {
+                    return None;
+                }
+                if i != scroll {
+                    walk_size += SPLITER.len();
+                }
+                let w = min(
+                    widths[i],
+                    (strip_start + strip_width).saturating_sub(walk_size),
+                );
+                let range = (walk_size, w);
+                walk_size += w;
+                Some(range)
+            })
+            .collect();
+        TabStripLayout {
+            ranges,
+            overflow_left: scroll > 0,
+            overflow_right: end < tabs_count,
+        }
+    }
+    /// 根据 x 坐标找到命中的区域，x 是相对于 tab 栏的坐标
+    pub fn hit_test(&self, x: usize) -> Option<TabBarHit> {
+        let width = self.width.get();
+        let layout = self.strip_layout(width);
+        if layout.overflow_left && x == 0 {
+            return Some(TabBarHit::ScrollLeft);
+        }
+        if layout.overflow_right && x + 1 == width {
+            return Some(TabBarHit::ScrollRight);
+        }
+        layout
+            .ranges
+            .iter()
+            .position(|range| matches!(range, Some((start, w)) if x >= *start && x < start + w))
+            .map(TabBarHit::Tab)
     }
     // pub fn set_width(self: &mut TabBar, width: usize) {
     //     self.view
@@ -46,29 +246,148 @@ impl BrowserTabBarViewer {
 }
 impl View for BrowserTabBarViewer {
     fn draw(&self, printer: &Printer) {
-        let mut walk_size: usize = 0;
-        let unit_size = printer.size.x / max(self.tabs.borrow().len(), 1);
+        let layout = self.strip_layout(printer.size.x);
         // printer.print((5, 5), "qaq");
         // printer.print((0, 0), &self.tabs.len().to_string());
+        let arrow_color = ColorStyle::front(BaseColor::Cyan.dark());
+        if layout.overflow_left {
+            printer.with_color(arrow_color, |printer| printer.print((0, 0), ARROW_LEFT));
+        }
+        if layout.overflow_right {
+            printer.with_color(arrow_color, |printer| {
+                printer.print((printer.size.x.saturating_sub(1), 0), ARROW_RIGHT)
+            });
+        }
+        let mut is_first = true;
         for (i, tab) in self.tabs.borrow().iter().enumerate() {
-            let mut spliter = "|";
-            if i == 0 {
-                spliter = ""
+            let (start, width) = match layout.ranges[i] {
+                Some(range) => range,
+                None => continue,
+            };
+            if !is_first {
+                printer.print(Vec2::new(start - SPLITER.len(), 0), SPLITER);
             }
-            printer.print(Vec2::new(walk_size, 0), spliter);
+            is_first = false;
             let tab_printer = &printer
-                .offset(Vec2::new(walk_size + spliter.len(), 0))
-                .cropped(Vec2::new(unit_size - spliter.len(), 1));
-            tab.borrow().draw(tab_printer);
-
-            walk_size += unit_size;
+                .offset(Vec2::new(start, 0))
+                .cropped(Vec2::new(width, 1));
+            let draw_tab = |printer: &Printer| {
+                let mut hint_width = 0;
+                if let Some(hint) = Self::shortcut_hint(i) {
+                    printer.with_color(ColorStyle::front(BaseColor::Cyan.dark()), |printer| {
+                        printer.with_effect(Effect::Underline, |printer| {
+                            printer.print((0, 0), &hint);
+                        });
+                    });
+                    hint_width = hint.len() + 1;
+                }
+                let tab_printer = printer.offset((hint_width, 0));
+                tab.borrow().draw(&tab_printer);
+            };
+            if self.selected.get() == Some(i) {
+                // 选中的 tab 反色显示
+                tab_printer.with_effect(Effect::Reverse, |printer| {
+                    printer.print_hline((0, 0), printer.size.x, " ");
+                    printer.with_effect(Effect::Bold, draw_tab);
+                });
+            } else {
+                draw_tab(tab_printer);
+            }
         }
     }
-    fn required_size(&mut self, _constraint: Vec2) -> Vec2 {
-        let mut min_width = max(1, _constraint.x);
-        for tab in self.tabs.borrow().iter() {
-            min_width = max(tab.borrow_mut().required_size(_constraint).x, min_width)
-        }
-        Vec2::new(min_width, 1)
+    fn layout(&mut self, size: Vec2) {
+        self.width.set(size.x);
+    }
+    fn required_size(&mut self, constraint: Vec2) -> Vec2 {
+        Vec2::new(max(1, constraint.x), 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::truncate_str;
+
+    fn tab_bar(titles: &[&str], policy: TabWidthPolicy) -> BrowserTabBarViewer {
+        let mut bar = BrowserTabBarViewer::new();
+        bar.set_tabs(
+            titles
+                .iter()
+                .map(|title| Rc::new(RefCell::new(PageTab::new(title.to_string()))))
+                .collect(),
+        );
+        bar.set_width_policy(policy);
+        bar
+    }
+
+    #[test]
+    fn even_tabs_share_the_width() {
+        let bar = tab_bar(&["build", "tsc", "dev"], TabWidthPolicy::Even);
+        let layout = bar.strip_layout(62);
+        assert_eq!(
+            layout.ranges,
+            [Some((0, 20)), Some((21, 20)), Some((42, 20))]
+        );
+        assert!(!layout.overflow_left && !layout.overflow_right);
+    }
+
+    #[test]
+    fn proportional_tabs_follow_the_title_width() {
+        // 加上快捷键提示 `[1] ` 后自然宽度分别是 8 和 16
+        let bar = tab_bar(&["dev!", "dependencies"], TabWidthPolicy::Proportional);
+        let layout = bar.strip_layout(49);
+        assert_eq!(layout.ranges, [Some((0, 16)), Some((17, 32))]);
+    }
+
+    #[test]
+    fn narrow_tabs_overflow_and_scroll_to_the_selection() {
+        let titles = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"];
+        let mut bar = tab_bar(&titles, TabWidthPolicy::Even);
+        // 平分后每个 tab 不到 MIN_TAB_WIDTH，改为滚动显示，两侧留出箭头
+        let layout = bar.strip_layout(30);
+        assert_eq!(
+            &layout.ranges[..4],
+            [Some((1, 8)), Some((10, 8)), Some((19, 8)), None]
+        );
+        assert!(!layout.overflow_left && layout.overflow_right);
+
+        bar.set_selected(Some(5));
+        let layout = bar.strip_layout(30);
+        assert_eq!(
+            layout.ranges[2..7],
+            [None, Some((1, 8)), Some((10, 8)), Some((19, 8)), None]
+        );
+        assert!(layout.overflow_left && layout.overflow_right);
+
+        // 滚动位置会保留，选中的 tab 已经可见时不再滚动
+        bar.set_selected(Some(4));
+        assert_eq!(bar.strip_layout(30).ranges[3], Some((1, 8)));
+        bar.set_selected(Some(0));
+        assert!(!bar.strip_layout(30).overflow_left);
+    }
+
+    #[test]
+    fn hit_test_matches_the_drawn_layout() {
+        let titles = ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j"];
+        let mut bar = tab_bar(&titles, TabWidthPolicy::Even);
+        bar.set_selected(Some(5));
+        bar.layout(Vec2::new(30, 1));
+        assert_eq!(bar.hit_test(0), Some(TabBarHit::ScrollLeft));
+        assert_eq!(bar.hit_test(29), Some(TabBarHit::ScrollRight));
+        assert_eq!(bar.hit_test(1), Some(TabBarHit::Tab(3)));
+        assert_eq!(bar.hit_test(8), Some(TabBarHit::Tab(3)));
+        // 分隔符不属于任何 tab
+        assert_eq!(bar.hit_test(9), None);
+        assert_eq!(bar.hit_test(26), Some(TabBarHit::Tab(5)));
+        assert_eq!(bar.hit_test(27), None);
+    }
+
+    #[test]
+    fn truncates_titles_by_display_width() {
+        assert_eq!(truncate_str("tsc", 3), "tsc");
+        assert_eq!(truncate_str("构建日志", 5), "构建…");
+        assert_eq!(truncate_str("构建日志", 4), "构…");
+        assert_eq!(truncate_str("dependencies", 5), "depe…");
+        assert_eq!(truncate_str("dev", 0), "");
     }
 }
@@ -0,0 +1,238 @@
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::output_parser::{LineSplitter, OutputParser, ParserStatus};
+use crate::page_tab::{PageTab, TabStatus};
+
+use regex::{Captures, Regex};
+
+lazy_static! {
+    /// `tsc -w` 与 `tsc --build -w` 每一轮编译开始时的提示
+    static ref CYCLE_START: Regex = Regex::new(
+        r"Starting compilation in watch mode|Starting incremental compilation"
+    )
+    .unwrap();
+    /// 每一轮编译结束时的统计，与 TS 版本 `TscPanel.writeTscLog` 的匹配规则一致
+    static ref FOUND_ERRORS: Regex = Regex::new(r"Found (\d+) errors?").unwrap();
+    /// `src/a.ts(1,7): error TS2322: ...`
+    static ref PLAIN_DIAGNOSTIC: Regex = Regex::new(
+        r"^(.+?)\((\d+),(\d+)\): (error|warning|message) (TS\d+): (.*)$"
+    )
+    .unwrap();
+    /// `--pretty` 模式：`src/a.ts:1:7 - error TS2322: ...`
+    static ref PRETTY_DIAGNOSTIC: Regex = Regex::new(
+        r"^(.+?):(\d+):(\d+) - (error|warning|message) (TS\d+): (.*)$"
+    )
+    .unwrap();
+}
+
+/// tsc 输出的解析器：识别 watch 模式的每一轮编译与其中的诊断信息，
+/// 编译中显示 Loading，结束时根据错误数量显示 Error 或者 Success
+pub struct TscParser {
+    lines: LineSplitter,
+    diagnostics: Vec<Diagnostic>,
+    /// 诊断信息之后缩进的行是消息的后续内容，直到遇到空行
+    continuing: bool,
+    /// 由解析器添加到 tab 上的状态，新一轮编译开始时移除
+    status: ParserStatus,
+}
+
+impl TscParser {
+    pub fn new() -> Self {
+        TscParser {
+            lines: LineSplitter::new(),
+            diagnostics: Vec::new(),
+            continuing: false,
+            status: ParserStatus::new(),
+        }
+    }
+
+    fn parse_line(&mut self, line: &str, tab: &mut PageTab) {
+        let line = line.trim_end();
+        if self.continuing && line.starts_with(char::is_whitespace) {
+            if let Some(diagnostic) = self.diagnostics.last_mut() {
+                diagnostic.message.push('\n');
+                diagnostic.message.push_str(line.trim());
+            }
+            return;
+        }
+        self.continuing = false;
+
+        if CYCLE_START.is_match(line) {
+            self.status.clear(tab);
+            self.diagnostics.clear();
+            self.status
+                .add(tab, TabStatus::Loading, "compiling".to_owned());
+        } else if let Some(captures) = FOUND_ERRORS.captures(line) {
+            let count: usize = captures[1].parse().unwrap_or(0);
+            self.status.remove(tab, TabStatus::Loading);
+            if count == 0 {
+                self.status
+                    .add(tab, TabStatus::Success, "no errors".to_owned());
+            } else if !self.has_errors() {
+                // 没有识别出具体的诊断信息时，至少保留统计结果
+                self.status
+                    .add(tab, TabStatus::Error, captures[0].to_owned());
+            }
+        } else if let Some(diagnostic) = PLAIN_DIAGNOSTIC
+            .captures(line)
+            .or_else(|| PRETTY_DIAGNOSTIC.captures(line))
+            .and_then(|captures| to_diagnostic(&captures))
+        {
+            let status = match diagnostic.severity {
+                Severity::Error => Some(TabStatus::Error),
+                Severity::Warning => Some(TabStatus::Warn),
+                Severity::Message => None,
+            };
+            if let Some(status) = status {
+                let reason = format!(
+                    "{} {}",
+                    diagnostic.location(),
+                    diagnostic.code.as_deref().unwrap_or_default()
+                );
+                self.status.add(tab, status, reason);
+            }
+            self.diagnostics.push(diagnostic);
+            self.continuing = true;
+        }
+    }
+
+    fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+}
+
+impl OutputParser for TscParser {
+    fn feed(&mut self, text: &str, tab: &mut PageTab) {
+        for line in self.lines.feed(text) {
+            self.parse_line(&line, tab);
+        }
+    }
+    fn reset(&mut self, tab: &mut PageTab) {
+        self.status.clear(tab);
+        self.diagnostics.clear();
+        self.continuing = false;
+        self.lines = LineSplitter::new();
+    }
+    fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+}
+
+fn to_diagnostic(captures: &Captures) -> Option<Diagnostic> {
+    Some(Diagnostic {
+        file: captures[1].trim().to_owned(),
+        line: captures[2].parse().ok()?,
+        column: captures[3].parse().ok()?,
+        severity: Severity::parse(&captures[4])?,
+        code: Some(captures[5].to_owned()),
+        message: captures[6].to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAIN: &str = "\
+[10:00:00 AM] Starting compilation in watch mode...
+
+src/a.ts(3,7): error TS2322: Type 'string' is not assignable to type 'number'.
+src/b.ts(10,1): error TS2304: Cannot find name 'foo'.
+  Did you mean 'for'?
+[10:00:01 AM] Found 2 errors. Watching for file changes.
+";
+
+    /// `tsc -w --pretty` 的输出，带有清屏与颜色
+    const PRETTY: &str = "\x1b[2J\x1b[3J\x1b[H[\x1b[90m10:00:00 AM\x1b[0m] Starting compilation in watch mode...
+
+\x1b[96msrc/a.ts\x1b[0m:\x1b[93m3\x1b[0m:\x1b[93m7\x1b[0m - \x1b[91merror\x1b[0m\x1b[90m TS2322: \x1b[0mType 'string' is not assignable to type 'number'.
+
+\x1b[7m3\x1b[0m const x: number = \"a\";
+\x1b[7m \x1b[0m \x1b[91m      ~\x1b[0m
+
+[\x1b[90m10:00:01 AM\x1b[0m] Found 1 error. Watching for file changes.
+";
+
+    fn feed_all(parser: &mut TscParser, tab: &mut PageTab, chunks: &[&str]) {
+        for chunk in chunks {
+            parser.feed(chunk, tab);
+        }
+    }
+
+    #[test]
+    fn parses_plain_output() {
+        let mut parser = TscParser::new();
+        let mut tab = PageTab::new("tsc".to_owned());
+        parser.feed(PLAIN, &mut tab);
+        assert_eq!(
+            tab.status_reasons(),
+            vec![(
+                TabStatus::Error,
+                vec!["src/a.ts:3:7 TS2322", "src/b.ts:10:1 TS2304"]
+            )]
+        );
+        let diagnostics = parser.diagnostics();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[1].code.as_deref(), Some("TS2304"));
+        assert_eq!(
+            diagnostics[1].message,
+            "Cannot find name 'foo'.\nDid you mean 'for'?"
+        );
+    }
+
+    #[test]
+    fn parses_pretty_output() {
+        let mut parser = TscParser::new();
+        let mut tab = PageTab::new("tsc".to_owned());
+        parser.feed(PRETTY, &mut tab);
+        assert_eq!(
+            tab.status_reasons(),
+            vec![(TabStatus::Error, vec!["src/a.ts:3:7 TS2322"])]
+        );
+        assert_eq!(
+            parser.diagnostics()[0].message,
+            "Type 'string' is not assignable to type 'number'."
+        );
+    }
+
+    #[test]
+    fn parses_chunks_split_mid_line() {
+        let mut parser = TscParser::new();
+        let mut tab = PageTab::new("tsc".to_owned());
+        feed_all(
+            &mut parser,
+            &mut tab,
+            &["[10:00:00 AM] Starting compil", "ation in watch mode...\n"],
+        );
+        assert_eq!(
+            tab.status_reasons(),
+            vec![(TabStatus::Loading, vec!["compiling"])]
+        );
+        // 在转义序列与诊断信息的中间截断
+        let split = PRETTY.find("\x1b[93m3").unwrap() + 3;
+        let (head, tail) = PRETTY.split_at(split);
+        feed_all(&mut parser, &mut tab, &[head, tail]);
+        assert_eq!(
+            tab.status_reasons(),
+            vec![(TabStatus::Error, vec!["src/a.ts:3:7 TS2322"])]
+        );
+    }
+
+    #[test]
+    fn new_cycle_replaces_previous_result() {
+        let mut parser = TscParser::new();
+        let mut tab = PageTab::new("tsc".to_owned());
+        parser.feed(PLAIN, &mut tab);
+        parser.feed(
+            "[10:00:05 AM] File change detected. Starting incremental compilation...\n\n\
+             [10:00:06 AM] Found 0 errors. Watching for file changes.\n",
+            &mut tab,
+        );
+        assert_eq!(
+            tab.status_reasons(),
+            vec![(TabStatus::Success, vec!["no errors"])]
+        );
+        assert!(parser.diagnostics().is_empty());
+    }
+}
@@ -0,0 +1,37 @@
+use std::borrow::Cow;
+use std::time::{SystemTime, UNIX_EPOCH};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+pub const ELLIPSIS: &str = "…";
+
+/// 按显示宽度截断文本，超出时以 "…" 结尾；CJK 等宽字符按 2 格计算
+pub fn truncate_str(text: &str, width: usize) -> Cow<'_, str> {
+    if text.width() <= width {
+        return Cow::Borrowed(text);
+    }
+    if width == 0 {
+        return Cow::Borrowed("");
+    }
+    let mut result = String::new();
+    let mut walk_width = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        if walk_width + char_width > width - ELLIPSIS.width() {
+            break;
+        }
+        walk_width += char_width;
+        result.push(c);
+    }
+    result.push_str(ELLIPSIS);
+    Cow::Owned(result)
+}
+
+/// 本地时间的 `HH:MM:SS`
+pub fn format_time(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs()) as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    unsafe { libc::localtime_r(&seconds, &mut tm) };
+    format!("{:02}:{:02}:{:02}", tm.tm_hour, tm.tm_min, tm.tm_sec)
+}
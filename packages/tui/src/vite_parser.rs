@@ -0,0 +1,245 @@
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::output_parser::{LineSplitter, OutputParser, ParserStatus};
+use crate::page_tab::{PageTab, TabStatus};
+
+use regex::Regex;
+
+lazy_static! {
+    /// `vite build --watch` 每次开始构建时的提示
+    static ref BUILD_START: Regex =
+        Regex::new(r"^(?:build started|vite v\S+ building for \w+)").unwrap();
+    static ref BUILT_IN: Regex = Regex::new(r"built in (\d+(?:\.\d+)?m?s)").unwrap();
+    /// vite 与 rollup 的错误，之后通常跟着 `file:` 与代码帧；
+    /// `[vite] ` 开头的其它行（`hmr update`、`connected.` 等）只是普通的提示
+    static ref ERROR: Regex = Regex::new(
+        r"^(?:error during build:\s*|\[vite:[\w-]+\] |\[vite\] (?:Internal server error: |error:? )|(?:\[\w+\] )?(?:\w*Error): |✘ \[ERROR\] )(.*)$"
+    )
+    .unwrap();
+    /// rollup 的 `(!) ...` 与 esbuild 的 `▲ [WARNING] ...`
+    static ref WARNING: Regex = Regex::new(r"^(?:\(!\) |▲ \[WARNING\] |warning: )(.*)$").unwrap();
+    /// 错误所在的位置：`file: /src/a.ts:3:4`
+    static ref LOCATION: Regex = Regex::new(r"^\s*file: (.+?):(\d+):(\d+)").unwrap();
+    static ref ESBUILD_DETAIL: Regex = Regex::new(r"^\S+:\d+:\d+: (?:ERROR|WARNING): ").unwrap();
+}
+
+/// 代码帧中的行：`1: import x`、`   ^`、`  |`，
+/// 以及 esbuild 在 `file:` 之前列出的错误详情：`/src/a.ts:3:4: ERROR: ...`
+fn is_code_frame(line: &str) -> bool {
+    let line = line.trim_start();
+    ESBUILD_DETAIL.is_match(line)
+        || line.starts_with('^')
+        || line.starts_with('|')
+        || line.split_once(':').is_some_and(|(number, _)| {
+            !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+        })
+}
+
+/// vite/rollup 输出的解析器：识别构建的开始与结束、警告和错误，
+/// 连续相同的行会被合并为一行
+pub struct ViteParser {
+    lines: LineSplitter,
+    diagnostics: Vec<Diagnostic>,
+    /// 最近一条错误的消息，等待后面的 `file:` 行补充位置
+    last_error: Option<String>,
+    /// 由解析器添加到 tab 上的状态，新一轮构建开始时移除
+    status: ParserStatus,
+}
+
+impl ViteParser {
+    pub fn new() -> Self {
+        ViteParser {
+            lines: LineSplitter::new(),
+            diagnostics: Vec::new(),
+            last_error: None,
+            status: ParserStatus::new(),
+        }
+    }
+
+    fn parse_line(&mut self, line: &str, tab: &mut PageTab) {
+        let line = line.trim_end();
+        if BUILD_START.is_match(line) {
+            self.status.clear(tab);
+            self.diagnostics.clear();
+            self.last_error = None;
+            self.status
+                .add(tab, TabStatus::Loading, "building".to_owned());
+        } else if let Some(captures) = BUILT_IN.captures(line) {
+            self.status.remove(tab, TabStatus::Loading);
+            if !self.status.has(TabStatus::Error) {
+                self.status.add(
+                    tab,
+                    TabStatus::Success,
+                    format!("built in {}", &captures[1]),
+                );
+            }
+        } else if let Some(captures) = LOCATION.captures(line) {
+            if let Some(message) = self.last_error.take() {
+                self.diagnostics.push(Diagnostic {
+                    file: captures[1].to_owned(),
+                    line: captures[2].parse().unwrap_or(1),
+                    column: captures[3].parse().unwrap_or(1),
+                    severity: Severity::Error,
+                    code: None,
+                    message,
+                });
+            }
+        } else if let Some(captures) = ERROR.captures(line) {
+            let message = captures[1].trim().to_owned();
+            if message.is_empty() {
+                // `error during build:` 之后的一行才是具体的错误
+                return;
+            }
+            self.status.remove(tab, TabStatus::Loading);
+            self.status.add(tab, TabStatus::Error, message.clone());
+            self.last_error = Some(message);
+        } else if let Some(captures) = WARNING.captures(line) {
+            self.status
+                .add(tab, TabStatus::Warn, captures[1].trim().to_owned());
+        } else if !is_code_frame(line) && !line.trim().is_empty() {
+            self.last_error = None;
+        }
+    }
+}
+
+impl OutputParser for ViteParser {
+    fn feed(&mut self, text: &str, tab: &mut PageTab) {
+        for line in self.lines.feed(text) {
+            self.parse_line(&line, tab);
+        }
+    }
+    fn collapse_duplicates(&self) -> bool {
+        true
+    }
+    fn reset(&mut self, tab: &mut PageTab) {
+        self.status.clear(tab);
+        self.diagnostics.clear();
+        self.last_error = None;
+        self.lines = LineSplitter::new();
+    }
+    fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUILD: &str = "\
+\x1b[36mvite v5.0.0 \x1b[32mbuilding for production...\x1b[36m\x1b[39m
+transforming...
+\x1b[32m✓\x1b[39m 32 modules transformed.
+\x1b[33m
+(!) Some chunks are larger than 500 kB after minification.\x1b[39m
+\x1b[2mdist/\x1b[22m\x1b[36massets/index-4f1c2a.js  \x1b[39m\x1b[1m\x1b[2m612.30 kB\x1b[22m\x1b[1m\x1b[22m
+\x1b[32m✓ built in 1.23s\x1b[39m
+";
+
+    /// `vite build --watch` 中的错误与代码帧
+    const ERROR_BUILD: &str = "\
+build started...
+transforming...
+\x1b[31m[vite:esbuild] Transform failed with 1 error:
+/app/src/main.ts:2:10: ERROR: Expected \";\" but found \"b\"\x1b[39m
+file: \x1b[36m/app/src/main.ts:2:10\x1b[31m
+\x1b[33m
+Expected \";\" but found \"b\"
+1  |  import './style.css'
+2  |  const a b = 1
+   |          ^
+\x1b[39m
+";
+
+    #[test]
+    fn parses_successful_build() {
+        let mut parser = ViteParser::new();
+        let mut tab = PageTab::new("vite".to_owned());
+        parser.feed(BUILD, &mut tab);
+        assert_eq!(
+            tab.status_reasons(),
+            vec![
+                (
+                    TabStatus::Warn,
+                    vec!["Some chunks are larger than 500 kB after minification."]
+                ),
+                (TabStatus::Success, vec!["built in 1.23s"]),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_error_with_code_frame() {
+        let mut parser = ViteParser::new();
+        let mut tab = PageTab::new("vite".to_owned());
+        parser.feed(BUILD, &mut tab);
+        parser.feed(ERROR_BUILD, &mut tab);
+        assert_eq!(
+            tab.status_reasons(),
+            vec![(TabStatus::Error, vec!["Transform failed with 1 error:"])]
+        );
+        assert_eq!(
+            parser.diagnostics(),
+            &[Diagnostic {
+                file: "/app/src/main.ts".to_owned(),
+                line: 2,
+                column: 10,
+                severity: Severity::Error,
+                code: None,
+                message: "Transform failed with 1 error:".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_chunks_split_mid_line() {
+        let mut parser = ViteParser::new();
+        let mut tab = PageTab::new("vite".to_owned());
+        for chunk in ERROR_BUILD.split_inclusive("a") {
+            parser.feed(chunk, &mut tab);
+        }
+        assert_eq!(
+            tab.status_reasons(),
+            vec![(TabStatus::Error, vec!["Transform failed with 1 error:"])]
+        );
+        assert_eq!(parser.diagnostics().len(), 1);
+        parser.feed("build st", &mut tab);
+        parser.feed("arted...\n", &mut tab);
+        assert_eq!(
+            tab.status_reasons(),
+            vec![(TabStatus::Loading, vec!["building"])]
+        );
+    }
+
+    #[test]
+    fn vite_notices_are_not_errors() {
+        let mut parser = ViteParser::new();
+        let mut tab = PageTab::new("vite".to_owned());
+        parser.feed(BUILD, &mut tab);
+        parser.feed(
+            "\x1b[2m10:21:07\x1b[22m \x1b[36m\x1b[1m[vite]\x1b[22m\x1b[39m hmr update /src/App.vue\n\
+             [vite] connected.\n\
+             [vite] page reload src/main.ts\n",
+            &mut tab,
+        );
+        assert!(parser.diagnostics().is_empty());
+        assert_eq!(
+            tab.status_reasons(),
+            vec![
+                (
+                    TabStatus::Warn,
+                    vec!["Some chunks are larger than 500 kB after minification."]
+                ),
+                (TabStatus::Success, vec!["built in 1.23s"]),
+            ]
+        );
+
+        parser.feed(
+            "[vite] Internal server error: Failed to resolve import \"./b\"\n",
+            &mut tab,
+        );
+        assert_eq!(
+            tab.status_reasons()[0],
+            (TabStatus::Error, vec!["Failed to resolve import \"./b\""])
+        );
+    }
+}
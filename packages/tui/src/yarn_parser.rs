@@ -0,0 +1,234 @@
+use crate::output_parser::{LineSplitter, OutputParser, ParserStatus};
+use crate::page_tab::{PageTab, TabStatus};
+
+use regex::Regex;
+
+lazy_static! {
+    /// `[2/4] Fetching packages...`
+    static ref STEP: Regex = Regex::new(r"^\[(\d+)/(\d+)\] (.+?)\.*$").unwrap();
+    /// 在终端中运行时，获取依赖阶段会用 `\r` 刷新进度条：`[####----] 123/456`
+    static ref PROGRESS_BAR: Regex = Regex::new(r"\[[#\-\s]*\]\s*(\d+)/(\d+)\s*$").unwrap();
+    static ref DONE: Regex = Regex::new(r"^(?:success Already up-to-date|Done in \S+?)\.?$").unwrap();
+    static ref ERROR: Regex = Regex::new(r"^error (.*)$").unwrap();
+    static ref WARNING: Regex = Regex::new(r"^warning (.*)$").unwrap();
+    static ref QUOTED: Regex = Regex::new(r#""([^"]+)""#).unwrap();
+}
+
+/// 从 yarn 的警告或者错误中取出相关的包名：
+/// `eslint > rimraf@2.6.3: ...` 取依赖链的最后一项，
+/// `Couldn't find package "foo@^1.0.0" ...` 取第一个引号中的内容
+fn package_name(message: &str) -> String {
+    if let Some((subject, _)) = message.split_once(": ") {
+        if !subject.contains(' ') || subject.contains(" > ") {
+            return last_in_chain(subject);
+        }
+    }
+    if let Some(captures) = QUOTED.captures(message) {
+        let name = last_in_chain(&captures[1]);
+        if !name.is_empty() {
+            return name;
+        }
+    }
+    message.to_owned()
+}
+
+fn last_in_chain(chain: &str) -> String {
+    chain
+        .rsplit(" > ")
+        .next()
+        .unwrap_or(chain)
+        .trim_matches(|c: char| c == '"' || c.is_whitespace())
+        .to_owned()
+}
+
+/// yarn v1 输出的解析器：根据 `[n/4]` 的阶段（resolving、fetching、linking、building）
+/// 与获取依赖时的进度条计算整体进度，警告和错误以包名作为状态的原因
+pub struct YarnParser {
+    lines: LineSplitter,
+    status: ParserStatus,
+    /// 当前阶段 (n, 总数)
+    step: Option<(usize, usize)>,
+}
+
+impl YarnParser {
+    pub fn new() -> Self {
+        YarnParser {
+            lines: LineSplitter::new(),
+            status: ParserStatus::new(),
+            step: None,
+        }
+    }
+
+    fn parse_line(&mut self, line: &str, tab: &mut PageTab) {
+        let line = line.trim();
+        if let Some(captures) = STEP.captures(line) {
+            let step: usize = captures[1].parse().unwrap_or(1);
+            let total: usize = captures[2].parse().unwrap_or(1).max(1);
+            if step <= 1 {
+                self.status.clear(tab);
+            }
+            self.status.remove(tab, TabStatus::Loading);
+            self.status
+                .add(tab, TabStatus::Loading, captures[3].to_owned());
+            self.step = Some((step, total));
+            self.update_progress(tab, 0.0);
+        } else if DONE.is_match(line) {
+            self.finish(tab);
+            if !self.status.has(TabStatus::Error) {
+                // `success Already up-to-date.` 之后还有 `Done in 0.5s.`，每次运行只保留一个
+                self.status.remove(tab, TabStatus::Success);
+                self.status.add(
+                    tab,
+                    TabStatus::Success,
+                    line.trim_start_matches("success ").to_owned(),
+                );
+            }
+        } else if let Some(captures) = ERROR.captures(line) {
+            self.finish(tab);
+            self.status
+                .add(tab, TabStatus::Error, package_name(&captures[1]));
+        } else if let Some(captures) = WARNING.captures(line) {
+            self.status
+                .add(tab, TabStatus::Warn, package_name(&captures[1]));
+        }
+    }
+
+    /// 阶段内的进度：当前阶段之前的阶段都算完成
+    fn update_progress(&self, tab: &mut PageTab, step_progress: f32) {
+        if let Some((step, total)) = self.step {
+            let done = step.saturating_sub(1) as f32 + step_progress;
+            tab.set_progress(Some(done / total as f32));
+        }
+    }
+    fn finish(&mut self, tab: &mut PageTab) {
+        self.step = None;
+        self.status.remove(tab, TabStatus::Loading);
+        tab.set_progress(None);
+    }
+}
+
+impl OutputParser for YarnParser {
+    fn feed(&mut self, text: &str, tab: &mut PageTab) {
+        for line in self.lines.feed(text) {
+            self.parse_line(&line, tab);
+        }
+        if let Some(captures) = PROGRESS_BAR.captures(self.lines.partial()) {
+            let done: f32 = captures[1].parse().unwrap_or(0.0);
+            let total: f32 = captures[2].parse().unwrap_or(1.0);
+            if total > 0.0 {
+                self.update_progress(tab, done / total);
+            }
+        }
+    }
+    fn reset(&mut self, tab: &mut PageTab) {
+        self.status.clear(tab);
+        self.step = None;
+        tab.set_progress(None);
+        self.lines = LineSplitter::new();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_all(parser: &mut YarnParser, tab: &mut PageTab, chunks: &[&str]) {
+        for chunk in chunks {
+            parser.feed(chunk, tab);
+        }
+    }
+
+    #[test]
+    fn tracks_steps_and_progress_bar() {
+        let mut parser = YarnParser::new();
+        let mut tab = PageTab::new("yarn".to_owned());
+        feed_all(
+            &mut parser,
+            &mut tab,
+            &[
+                "yarn install v1.22.19\n[1/4] Resolving packages...\n",
+                "warning eslint > rimraf@2.6.3: Rimraf versions prior to v4 are no longer supported\n",
+                "[2/4] Fetching packages...\n",
+                "\x1b[2K\x1b[1G[####------] 114/456",
+            ],
+        );
+        assert_eq!(
+            tab.status_reasons(),
+            vec![
+                (TabStatus::Warn, vec!["rimraf@2.6.3"]),
+                (TabStatus::Loading, vec!["Fetching packages"]),
+            ]
+        );
+        assert_eq!(tab.progress(), Some(1.25 / 4.0));
+        feed_all(
+            &mut parser,
+            &mut tab,
+            &[
+                "\x1b[2K\x1b[1G[########--] 456/456\n",
+                "[3/4] Linking dependencies...\n[4/4] Building fresh packages...\n",
+                "success Saved lockfile.\nDone in 3.21s.\n",
+            ],
+        );
+        assert_eq!(
+            tab.status_reasons(),
+            vec![
+                (TabStatus::Warn, vec!["rimraf@2.6.3"]),
+                (TabStatus::Success, vec!["Done in 3.21s."]),
+            ]
+        );
+        assert_eq!(tab.progress(), None);
+    }
+
+    #[test]
+    fn keeps_a_single_success_reason() {
+        let mut parser = YarnParser::new();
+        let mut tab = PageTab::new("yarn".to_owned());
+        parser.feed(
+            "yarn install v1.22.19\n[1/4] Resolving packages...\n\
+             success Already up-to-date.\nDone in 0.5s.\n",
+            &mut tab,
+        );
+        assert_eq!(
+            tab.status_reasons(),
+            vec![(TabStatus::Success, vec!["Done in 0.5s."])]
+        );
+    }
+
+    #[test]
+    fn reports_errors_by_package() {
+        let mut parser = YarnParser::new();
+        let mut tab = PageTab::new("yarn".to_owned());
+        parser.feed(
+            "yarn install v1.22.19\n[1/4] Resolving packages...\n\
+             error Couldn't find package \"left-padd@^1.0.0\" required by \"app@1.0.0\" on the \"npm\" registry.\n\
+             info Visit https://yarnpkg.com/en/docs/cli/install for documentation about this command.\n",
+            &mut tab,
+        );
+        assert_eq!(
+            tab.status_reasons(),
+            vec![(TabStatus::Error, vec!["left-padd@^1.0.0"])]
+        );
+    }
+
+    #[test]
+    fn parses_chunks_split_mid_line() {
+        let mut parser = YarnParser::new();
+        let mut tab = PageTab::new("yarn".to_owned());
+        feed_all(
+            &mut parser,
+            &mut tab,
+            &[
+                "[2/",
+                "4] Fetching pack",
+                "ages...\n",
+                "[#####-----] 22",
+                "8/456",
+            ],
+        );
+        assert_eq!(
+            tab.status_reasons(),
+            vec![(TabStatus::Loading, vec!["Fetching packages"])]
+        );
+        assert_eq!(tab.progress(), Some(1.5 / 4.0));
+    }
+}